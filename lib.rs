@@ -2,6 +2,10 @@
 
 #[ink::contract]
 mod erc20 {
+    use ink::env::call::{build_call, ExecutionInput, Selector};
+    use ink::env::hash::Blake2x256;
+    use ink::env::CallFlags;
+    use ink::prelude::{string::String, vec::Vec};
     use ink::storage::Mapping;
 
 
@@ -11,18 +15,75 @@ mod erc20 {
         balances: Mapping<AccountId, Balance>,
         allowances: Mapping<(AccountId, AccountId), Balance>,
         is_voting_happening: bool,
-        votes: Mapping<u128, u128>,
+        votes: Mapping<(u32, u128), u128>,
         time_to_vote: u64,
         voting_begin_time: u64,
         voting_end_time: u64,
-        already_voted: Mapping<AccountId, bool>,
+        already_voted: Mapping<(u32, AccountId), bool>,
         current_winner: u128,
         voting_number: u32,
         token_price: u128,
-        fee: u128,
-        fee_divider: u128,
-        weekly_fee_to_burn: u128,
-        time_lapsed_for_fee_to_burn: u64,
+        fees: FeeSchedule,
+        fee_exempt: Mapping<AccountId, bool>,
+        fee_to_burn: u128,
+        burn_epoch_length: u64,
+        burn_epoch_start: u64,
+        next_burn_epoch: u64,
+        burn_history: Mapping<u64, Balance>,
+        keeper_incentive: Permill,
+        buyback_pool: Option<AccountId>,
+        buyback_cap_per_epoch: Balance,
+        buyback_spent: (u64, Balance),
+        min_reserve_ratio: Permill,
+        treasury_quorum: Permill,
+        spend_proposals: Mapping<u32, SpendProposal>,
+        next_spend_proposal: u32,
+        spend_approved: Mapping<(u32, AccountId), bool>,
+        nonces: Mapping<AccountId, u64>,
+        admin: AccountId,
+        proposal_threshold: Permill,
+        voting_threshold: Permill,
+        pricing_curve: PricingCurve,
+        decimals: u8,
+        treasury: AccountId,
+        reward_per_token: u128,
+        reward_per_token_paid: Mapping<AccountId, u128>,
+        pending_rewards: Mapping<AccountId, Balance>,
+        reward_pool: Balance,
+        max_supply: Balance,
+        pending_max_supply: Option<(Balance, u64)>,
+        presale: Option<Presale>,
+        presale_purchased: Mapping<(u32, AccountId), Balance>,
+        vesting: Mapping<AccountId, VestingSchedule>,
+        airdrops: Mapping<u32, Airdrop>,
+        next_airdrop: u32,
+        airdrop_claimed: Mapping<(u32, u64), ClaimWord>,
+        auction: Option<DutchAuction>,
+        auction_clearing_prices: Mapping<u32, u128>,
+        auction_contributions: Mapping<AccountId, AuctionContribution>,
+        auction_refunds_owed: Balance,
+        rate_limits: RateLimits,
+        trade_windows: Mapping<AccountId, TradeWindow>,
+        block_volume: (BlockNumber, Balance),
+        total_staked: Balance,
+        stakes: Mapping<AccountId, Stake>,
+        unbonding: Mapping<AccountId, (Balance, u64)>,
+        emission_rate: Balance,
+        unbonding_period: u64,
+        staking_reward_per_share: u128,
+        staking_updated_at: u64,
+        locks: Mapping<AccountId, Vec<BalanceLock>>,
+        lock_managers: Mapping<AccountId, bool>,
+        reserved: Mapping<AccountId, Balance>,
+        total_reserved: Balance,
+        proposal_params: ProposalParams,
+        proposal_deposit: Option<(AccountId, Balance)>,
+        round_votes: Balance,
+        last_initiated: Mapping<AccountId, u64>,
+        compliance: AccountId,
+        blocklist: Mapping<AccountId, bool>,
+        allowlist: Mapping<AccountId, bool>,
+        allowlist_only: bool,
     }
 
     #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
@@ -33,571 +94,4284 @@ mod erc20 {
         VotingIsNotOngoing,
         VotingIsAlreadyOngoing,
         AccountAlreadyVoted,
-        AccountFrozenBecauseVoted,
         TimeToVoteNotElapsed,
         ExactAmountOfWeiRequired,
-        TimeForFeeBurnHasNotLapsed
+        TimeForFeeBurnHasNotLapsed,
+        TransferRejectedByReceiver,
+        PermitExpired,
+        InvalidSignature,
+        Overflow,
+        Underflow,
+        DivisionByZero,
+        NotAdmin,
+        InsufficientReserve,
+        NativeTransferFailed,
+        SlippageExceeded,
+        InvalidDecimals,
+        FeeTooHigh,
+        InvalidFeeTiers,
+        InvalidFeeSplit,
+        InvalidBurnEpochLength,
+        NotTreasury,
+        BuybackPoolNotSet,
+        BuybackCapExceeded,
+        InvalidPrice,
+        ReserveRatioTooLow,
+        CapExceeded,
+        TimelockNotElapsed,
+        NoPendingChange,
+        PresaleNotActive,
+        PresaleOngoing,
+        InvalidProof,
+        AllocationExceeded,
+        BalanceLocked,
+        AirdropNotFound,
+        AirdropExpired,
+        AirdropNotExpired,
+        AlreadyClaimed,
+        InvalidAuction,
+        AuctionOngoing,
+        AuctionNotEnded,
+        AuctionSupplyExceeded,
+        //carries the timestamp from which the trade fits again
+        RateLimited(u64),
+        InsufficientStake,
+        UnbondingNotElapsed,
+        NotLockManager,
+        TooManyLocks,
+        //carries the timestamp from which the account can start a round again
+        ProposalCooldown(u64),
+        NotCompliance,
+        SenderBlocked,
+        RecipientNotAllowed,
+        ProposalNotFound,
+        ProposalExpired,
+        ProposalAlreadyExecuted,
+        QuorumNotReached,
+        //the block's trade volume is used up, the trade fits again in a later block
+        BlockVolumeExceeded,
+        InvalidUnbondingPeriod,
+        BuybackTooSmall,
+        ReservedLockId,
+        InvalidPresale,
+        VestingScheduleMismatch,
+        BurnEpochNotFinalized
+    }
+
+    //mirrors the error returned by `PSP22Receiver::before_received` so the reply can be decoded
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum PSP22ReceiverError {
+        TransferRejected(String),
     }
 
     pub type Result<T> = core::result::Result<T, Error>;
 
-    #[ink(event)]
-    pub struct Transfer {
-        #[ink(topic)]
-        from: Option<AccountId>,
-        #[ink(topic)]
-        to: AccountId, 
-        value: Balance
+    //checked arithmetic used by every balance and supply mutation
+    fn safe_add(a: u128, b: u128) -> Result<u128> {
+        a.checked_add(b).ok_or(Error::Overflow)
     }
 
-    #[ink(event)]
-    pub struct Approval {
-        #[ink(topic)]
-        owner: AccountId,
-        #[ink(topic)]
-        spender: AccountId,
-        value: Balance,
+    fn safe_sub(a: u128, b: u128) -> Result<u128> {
+        a.checked_sub(b).ok_or(Error::Underflow)
     }
 
-    #[ink(event)]
-    pub struct VotingStartedTime {
-        #[ink(topic)]
-        voting_begin_time: u64,
-        voting_number: u32
+    fn safe_mul(a: u128, b: u128) -> Result<u128> {
+        a.checked_mul(b).ok_or(Error::Overflow)
     }
 
-    impl Erc20 {
-        #[ink(constructor)]
-        pub fn new(total_supply: Balance) -> Self {
-            let mut balances = Mapping::default();
-            let caller = Self::env().caller();
-            balances.insert(caller, &total_supply);
-            Self::env().emit_event(Transfer {
-                from: None,
-                to: caller,
-                value: total_supply,
-            });
-            Self { 
-                total_supply, 
-                balances,             
-                allowances: Mapping::default(),
-                is_voting_happening: false,
-                votes: Mapping::default(),
-                time_to_vote: 86400,
-                voting_begin_time: Default::default(),
-                voting_end_time: Default::default(),
-                already_voted: Mapping::default(),
-                current_winner: Default::default(),
-                voting_number: Default::default(),
-                token_price: 5,
-                fee: 1,
-                fee_divider: 100,
-                weekly_fee_to_burn: Default::default(),
-                time_lapsed_for_fee_to_burn: Default::default()
+    fn safe_div(a: u128, b: u128) -> Result<u128> {
+        a.checked_div(b).ok_or(Error::DivisionByZero)
+    }
+
+    //value * numerator / denominator, split on the denominator so large values don't overflow the product
+    fn mul_div_floor(value: u128, numerator: u128, denominator: u128) -> Result<u128> {
+        let whole = safe_mul(safe_div(value, denominator)?, numerator)?;
+        let rest = safe_div(safe_mul(value % denominator, numerator)?, denominator)?;
+        safe_add(whole, rest)
+    }
+
+    //parts per million, integer replacement for float ratios of the supply
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, scale::Encode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct Permill(u32);
+
+    impl Permill {
+        pub const ACCURACY: u32 = 1_000_000;
+
+        //values above 100% are clamped
+        pub const fn from_parts(parts: u32) -> Self {
+            if parts > Self::ACCURACY {
+                Self(Self::ACCURACY)
+            } else {
+                Self(parts)
             }
         }
 
-        #[ink(message)]
-        pub fn total_supply(&self) -> Balance {
-            self.total_supply
+        pub const fn from_percent(percent: u32) -> Self {
+            Self::from_parts(percent.saturating_mul(10_000))
         }
 
-        #[ink(message)]
-        pub fn balance_of(&self, owner: AccountId) -> Balance {
-            self.balances.get(owner).unwrap_or_default()
+        pub const fn deconstruct(self) -> u32 {
+            self.0
         }
 
-        #[ink(message)]
-        pub fn allowances(&self, token_owner: AccountId, spender: AccountId) -> Balance {
-            self.allowances.get(&(token_owner, spender)).unwrap_or_default()
+        //can't fail, parts never exceed ACCURACY
+        pub fn mul_floor(self, value: u128) -> u128 {
+            mul_div_floor(value, self.0 as u128, Self::ACCURACY as u128).unwrap_or_default()
         }
 
-        #[ink(message)]
-        pub fn get_current_timestemp(&self) -> u64 {
-            self.env().block_timestamp()
+        pub fn mul_ceil(self, value: u128) -> Result<u128> {
+            let accuracy = Self::ACCURACY as u128;
+            let parts = self.0 as u128;
+            let rest = safe_mul(value % accuracy, parts)?.div_ceil(accuracy);
+            safe_add(safe_mul(value / accuracy, parts)?, rest)
         }
+    }
 
-        #[ink(message)]
-        pub fn get_current_block_number(&self) -> u32 {
-            self.env().block_number()
+    //decoding clamps like from_parts, so message arguments can't carry more than 100%
+    impl scale::Decode for Permill {
+        fn decode<I: scale::Input>(input: &mut I) -> core::result::Result<Self, scale::Error> {
+            u32::decode(input).map(Self::from_parts)
         }
+    }
 
-        #[ink(message)]
-        pub fn transfer(&mut self, to: AccountId, tokens: u128) -> Result<()>{
-            let msg_sender: AccountId = self.env().caller();
-            let msg_sender_balance: Balance = self.balance_of(msg_sender);
-            if msg_sender_balance < tokens {
-                return Err(Error::InsufficientBalance);
-            } 
-            if self.already_voted.get(msg_sender).unwrap_or_default() {
-                return Err(Error::AccountFrozenBecauseVoted);
-            }
-            self.balances.insert(&msg_sender, &(msg_sender_balance - tokens));
-            let to_balance = self.balance_of(to);
-            self.balances.insert(&to, &(to_balance + tokens));
-            //event
-            self.env().emit_event(Transfer{
-                from: Some(msg_sender), 
-                to: to, 
-                value: tokens
-            });
-            Ok(())
-        }
+    //fixed point used for curve growth factors
+    const CURVE_ONE: u128 = 1_000_000_000;
 
-        #[ink(message)]
-        pub fn transfer_from(&mut self, from: AccountId, to: AccountId, tokens: Balance) -> Result<()> {
-            let msg_sender_balance = self.balance_of(from);
-            if msg_sender_balance < tokens {
-                return Err(Error::InsufficientBalance);
-            }
-            let allowance = self.allowances(from, to);
-            if allowance < tokens {
-                return Err(Error::InsufficientAllowance)
+    //multiplies two CURVE_ONE fixed point numbers without overflowing on the intermediate product
+    fn curve_mul(a: u128, b: u128) -> Result<u128> {
+        mul_div_floor(a, b, CURVE_ONE)
+    }
+
+    fn curve_pow(base: u128, mut exponent: u128) -> Result<u128> {
+        let mut result = CURVE_ONE;
+        let mut base = base;
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = curve_mul(result, base)?;
             }
-            if self.already_voted.get(from).unwrap_or_default() {
-                return Err(Error::AccountFrozenBecauseVoted);
+            exponent >>= 1;
+            if exponent > 0 {
+                base = curve_mul(base, base)?;
             }
-
-            self.balances.insert(&from, &(msg_sender_balance - tokens));
-
-            let to_balance = self.balance_of(to);
-            self.balances.insert(&to, &(to_balance + tokens));
-            self.env().emit_event(Transfer{
-                from: Some(from), 
-                to: to, 
-                value: tokens
-            });
-            Ok(())
         }
+        Ok(result)
+    }
 
-        #[ink(message)]
-        pub fn approve(&mut self, spender: AccountId, tokens: u128) -> Result<()>{
-            let msg_sender: AccountId = self.env().caller();
-            if self.balance_of(msg_sender) < tokens {
-                return Err(Error::InsufficientBalance);
+    //how buy and sell prices follow total_supply, `Flat` keeps using token_price
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub enum PricingCurve {
+        Flat,
+        //price(s) = base_price + slope * s
+        Linear { base_price: u128, slope: u128 },
+        //price(s) = base_price * (1 + growth)^(s / step)
+        Exponential { base_price: u128, growth: Permill, step: u128 },
+    }
+
+    impl PricingCurve {
+        //sum of the unit prices over the supply range [from, to), in closed form
+        fn cost(&self, flat_price: u128, from: u128, to: u128) -> Result<u128> {
+            let amount = safe_sub(to, from)?;
+            if amount == 0 {
+                return Ok(0);
+            }
+            match *self {
+                PricingCurve::Flat => safe_mul(amount, flat_price),
+                PricingCurve::Linear { base_price, slope } => {
+                    //slope * (from + ... + to - 1)
+                    let (even, odd) = if amount % 2 == 0 {
+                        (amount / 2, safe_add(from, to)? - 1)
+                    } else {
+                        (amount, (safe_add(from, to)? - 1) / 2)
+                    };
+                    safe_add(safe_mul(base_price, amount)?, safe_mul(slope, safe_mul(even, odd)?)?)
+                }
+                PricingCurve::Exponential { base_price, growth, step } => {
+                    let range = safe_sub(Self::geometric_prefix(growth, step, to)?, Self::geometric_prefix(growth, step, from)?)?;
+                    curve_mul(range, base_price)
+                }
             }
-    
-            self.allowances.insert((msg_sender, spender), &tokens);
-            self.env().emit_event(Approval {
-                owner: msg_sender,
-                spender: spender,
-                value: tokens
-            });
-            Ok(())
         }
 
-        #[ink(message)]
-        pub fn initiate_voting(&mut self, option: u128) -> Result<()>{
-            let caller: AccountId = self.env().caller();
-            if (self.balance_of(caller) as f32) < self.total_supply as f32 * 0.1 {
-                return Err(Error::InsufficientBalance);
-            } else if self.is_voting_happening || self.get_current_timestemp() < self.voting_end_time {
-                return Err(Error::VotingIsAlreadyOngoing);
-            } else {
-                self.voting_begin_time = self.get_current_timestemp();
-                self.voting_end_time = self.get_current_timestemp() + self.time_to_vote;
-                self.is_voting_happening = true;
-                self.vote(option).map_err(|err: Error| ink::env::debug_println!("{:?}", err)).ok();
-                self.voting_number+=1;
-            }
-            self.env().emit_event(VotingStartedTime{
-                voting_begin_time: self.voting_begin_time,
-                voting_number: self.voting_number
-            });
-            Ok(())
+        //sum of (1 + growth)^(s / step) for s in [0, supply), scaled by CURVE_ONE
+        fn geometric_prefix(growth: Permill, step: u128, supply: u128) -> Result<u128> {
+            let step = step.max(1);
+            let steps = supply / step;
+            let remainder = supply % step;
+            let rate = growth.deconstruct() as u128 * (CURVE_ONE / Permill::ACCURACY as u128);
+            let factor = curve_pow(safe_add(CURVE_ONE, rate)?, steps)?;
+            //(factor - 1) / rate, both in fixed point, or one per step without growth
+            let full_steps = match safe_mul(factor - CURVE_ONE, CURVE_ONE)?.checked_div(rate) {
+                Some(full_steps) => full_steps,
+                None => safe_mul(steps, CURVE_ONE)?,
+            };
+            safe_add(safe_mul(full_steps, step)?, safe_mul(remainder, factor)?)
         }
 
-        #[ink(message)]
-        pub fn vote(&mut self, price: u128) -> Result<()>{
-            let msg_sender: AccountId = self.env().caller();
-            if !self.is_voting_happening ||
-                self.env().block_timestamp() > self.voting_end_time || 
-                self.env().block_timestamp() < self.voting_begin_time
-            {
-                return Err(Error::VotingIsNotOngoing);
-            } else if (self.balance_of(msg_sender) as f32) < self.total_supply as f32 * 0.05 {
-                return Err(Error::InsufficientBalance);
-            } else if self.already_voted.get(msg_sender).unwrap_or(false) {
-                return Err(Error::AccountAlreadyVoted);
-            }
+        //price of the next unit at the given supply
+        fn spot_price(&self, flat_price: u128, supply: u128) -> Result<u128> {
+            self.cost(flat_price, supply, safe_add(supply, 1)?)
+        }
 
-            self.votes.insert(price, &self.balance_of(msg_sender));
-            self.already_voted.insert(msg_sender, &true);
-            if self.votes.get(self.current_winner).unwrap_or(0) == 0 {
-                self.current_winner = price;
-            } else if self.votes.get(self.current_winner).unwrap_or(0) < self.votes.get(price).unwrap_or(0) {
-                self.current_winner = price;
+        //governance moves the starting price of the curve
+        fn with_base_price(self, flat_price: u128) -> Self {
+            match self {
+                PricingCurve::Flat => PricingCurve::Flat,
+                PricingCurve::Linear { slope, .. } => PricingCurve::Linear { base_price: flat_price, slope },
+                PricingCurve::Exponential { growth, step, .. } => PricingCurve::Exponential { base_price: flat_price, growth, step },
             }
-            Ok(())
         }
+    }
 
-        #[ink(message)]
-        pub fn end_voting(&mut self) -> Result<()> {
-            if self.env().block_timestamp() < self.voting_end_time {
-                return Err(Error::TimeToVoteNotElapsed);
-            } else if self.env().block_timestamp() < self.voting_begin_time || !self.is_voting_happening {
-                return Err(Error::VotingIsNotOngoing);
+    //no fee in the schedule may exceed this
+    pub const MAX_FEE: Permill = Permill::from_percent(10);
+    pub const MAX_FEE_TIERS: usize = 8;
+
+    //trades of at least `min_amount` get `discount` off the base fee
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct FeeTier {
+        pub min_amount: Balance,
+        pub discount: Permill,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct FeeSchedule {
+        pub buy: Permill,
+        pub sell: Permill,
+        pub transfer: Permill,
+        //sorted by min_amount, at most MAX_FEE_TIERS
+        pub tiers: Vec<FeeTier>,
+        pub split: FeeSplit,
+    }
+
+    //where collected fees end up, the shares add up to 100%
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct FeeSplit {
+        pub burn: Permill,
+        pub treasury: Permill,
+        pub holders: Permill,
+    }
+
+    #[derive(Clone, Copy)]
+    enum FeeKind {
+        Buy,
+        Sell,
+        Transfer,
+    }
+
+    //which per account window a trade counts against
+    #[derive(Clone, Copy)]
+    enum TradeKind {
+        Buy,
+        Sell,
+    }
+
+    impl FeeSchedule {
+        fn validate(&self) -> Result<()> {
+            if self.buy > MAX_FEE || self.sell > MAX_FEE || self.transfer > MAX_FEE {
+                return Err(Error::FeeTooHigh);
+            }
+            if self.tiers.len() > MAX_FEE_TIERS || self.tiers.windows(2).any(|pair| pair[0].min_amount >= pair[1].min_amount) {
+                return Err(Error::InvalidFeeTiers);
+            }
+            let split = self.split.burn.deconstruct() as u64 + self.split.treasury.deconstruct() as u64 + self.split.holders.deconstruct() as u64;
+            if split != Permill::ACCURACY as u64 {
+                return Err(Error::InvalidFeeSplit);
             }
-            self.token_price = self.current_winner;
-            //setting all to default
-            self.is_voting_happening = false;
-            self.voting_begin_time = Default::default();
-            self.voting_end_time = Default::default();
-            self.votes = Mapping::default();
-            self.already_voted = Mapping::default();
-//add token price to the constructor
             Ok(())
         }
 
-        #[ink(message, payable)]
-        pub fn buy(&mut self, amount: u128) -> Result<()>{
-            let msg_sender = self.env().caller();
-            if self.already_voted.get(msg_sender).unwrap_or_default() {
-                return Err(Error::AccountFrozenBecauseVoted);
-            }
-            if self.env().transferred_value() == amount * self.token_price {
-                self.mint(amount).map_err(|err: Error| ink::env::debug_println!("{:?}", err)).ok();
-                //counting fee
-                let fee_to_take = (amount * self.fee) / self.fee_divider;
-                //balance + amount - fee
-                self.balances.insert(msg_sender, &(self.balance_of(msg_sender) + amount - fee_to_take));
-                self.weekly_fee_to_burn += fee_to_take;
-            } else {
-                return Err(Error::ExactAmountOfWeiRequired);
+        fn fee_for(&self, kind: FeeKind, amount: Balance) -> Balance {
+            let rate = match kind {
+                FeeKind::Buy => self.buy,
+                FeeKind::Sell => self.sell,
+                FeeKind::Transfer => self.transfer,
+            };
+            let fee = rate.mul_floor(amount);
+            match self.tiers.iter().rev().find(|tier| tier.min_amount <= amount) {
+                Some(tier) => fee - tier.discount.mul_floor(fee),
+                None => fee,
             }
-            Ok(())
         }
+    }
 
-        #[ink(message, payable)]
-        pub fn burn_fee_weekly (&mut self) -> Result<()> {
-            let msg_sender = self.env().caller();
-            if self.time_lapsed_for_fee_to_burn + 604800 < self.env().block_timestamp() {
-                return Err(Error::TimeForFeeBurnHasNotLapsed);
-            } 
-            self.burn(self.weekly_fee_to_burn, msg_sender).map_err(|err: Error| ink::env::debug_println!("{:?}", err)).ok();
-            self.weekly_fee_to_burn = 0;
-            self.time_lapsed_for_fee_to_burn = self.env().block_timestamp();
-            Ok(())
-        }
+    //scale of reward_per_token
+    const REWARD_PRECISION: u128 = 1_000_000_000_000_000_000;
 
-        #[ink(message)]
-        pub fn mint(&mut self, value: u128) -> Result<()> {
-            self.total_supply += value;            
-            Ok(())
+    fn reward_for(value: Balance, per_token: u128) -> Result<Balance> {
+        mul_div_floor(value, per_token, REWARD_PRECISION)
+    }
+
+    //delay between proposing and applying a new max_supply
+    pub const MAX_SUPPLY_TIMELOCK: u64 = 172800;
+
+    //how long holders have to approve a treasury spend
+    pub const SPEND_APPROVAL_PERIOD: u64 = 259200;
+
+    //native currency leaves the treasury once approvals reach the treasury quorum
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct SpendProposal {
+        pub to: AccountId,
+        pub amount: Balance,
+        pub approvals: Balance,
+        pub deadline: u64,
+        pub executed: bool,
+    }
+
+    pub type LockIdentifier = [u8; 8];
+
+    //holds a voter's balance until the round ends
+    pub const VOTE_LOCK: LockIdentifier = *b"voting  ";
+
+    //holds an approver's balance until the spend proposal closes
+    pub const SPEND_LOCK: LockIdentifier = *b"treasury";
+
+    pub const MAX_LOCKS: usize = 16;
+
+    //locks overlap, the largest active one is what can't be spent
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct BalanceLock {
+        pub id: LockIdentifier,
+        pub amount: Balance,
+        //active while block_timestamp < until
+        pub until: u64,
+    }
+
+    //a week between unstaking and being able to withdraw
+    pub const DEFAULT_UNBONDING_PERIOD: u64 = 604800;
+
+    //longest merkle proof accepted, enough for 2^32 leaves
+    pub const MAX_PROOF_LENGTH: usize = 32;
+
+    //hashes the pair in sorted order so proofs don't need left/right flags
+    fn hash_pair(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+        let (first, second) = if a <= b { (a, b) } else { (b, a) };
+        let mut output = [0u8; 32];
+        ink::env::hash_encoded::<Blake2x256, _>(&(first, second), &mut output);
+        output
+    }
+
+    fn verify_merkle_proof(root: [u8; 32], leaf: [u8; 32], proof: &[[u8; 32]]) -> bool {
+        if proof.len() > MAX_PROOF_LENGTH {
+            return false;
         }
+        proof.iter().fold(leaf, |node, sibling| hash_pair(&node, sibling)) == root
+    }
 
-        #[ink(message, payable)]
-        pub fn sell(&mut self, amount: u128) -> Result<()>{
-            let msg_sender = self.env().caller();
-            if self.already_voted.get(msg_sender).unwrap_or_default() {
-                return Err(Error::AccountFrozenBecauseVoted);
+    fn presale_leaf(account: AccountId, max_allocation: Balance, price: u128) -> [u8; 32] {
+        let mut output = [0u8; 32];
+        ink::env::hash_encoded::<Blake2x256, _>(&(account, max_allocation, price), &mut output);
+        output
+    }
+
+    //256 claim bits of one airdrop
+    pub type ClaimWord = [u8; 32];
+
+    fn airdrop_leaf(index: u64, account: AccountId, amount: Balance) -> [u8; 32] {
+        let mut output = [0u8; 32];
+        ink::env::hash_encoded::<Blake2x256, _>(&(index, account, amount), &mut output);
+        output
+    }
+
+    //unclaimed tokens wait in the fee pool until claimed or swept
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct Airdrop {
+        pub merkle_root: [u8; 32],
+        pub total: Balance,
+        pub claimed: Balance,
+        pub expiry: u64,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct VestingTerms {
+        pub cliff: u64,
+        pub duration: u64,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct Stake {
+        pub amount: Balance,
+        pub reward_per_share_paid: u128,
+        pub pending_rewards: Balance,
+    }
+
+    //the deposit is reserved when a round starts and slashed if the round misses quorum
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct ProposalParams {
+        pub deposit: Balance,
+        //votes needed, as a share of the supply at the end of the round
+        pub quorum: Permill,
+        //time between two rounds started by the same account
+        pub cooldown: u64,
+    }
+
+    //zero switches a limit off
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct RateLimits {
+        pub window: u64,
+        pub max_bought_per_window: Balance,
+        pub max_sold_per_window: Balance,
+        //bought and sold by everyone in one block
+        pub max_volume_per_block: Balance,
+    }
+
+    //an account's window opens with its first trade after the previous one expired
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct TradeWindow {
+        pub start: u64,
+        pub bought: Balance,
+        pub sold: Balance,
+    }
+
+    //price falls linearly from start_price to floor_price between start and end
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct DutchAuction {
+        pub id: u32,
+        pub start: u64,
+        pub end: u64,
+        pub start_price: u128,
+        pub floor_price: u128,
+        pub supply: Balance,
+        pub sold: Balance,
+        //buyers get back what they paid above the clearing price
+        pub refund_to_clearing: bool,
+        //price of the purchase that sold it out
+        pub sold_out_price: Option<u128>,
+    }
+
+    impl DutchAuction {
+        fn price_at(&self, now: u64) -> u128 {
+            if now <= self.start {
+                return self.start_price;
             }
-            //counting fee
-            let fee_to_take = (amount * self.fee) / self.fee_divider;
-            if self.balance_of(msg_sender) < amount + fee_to_take {
-                return Err(Error::InsufficientBalance);
-            } else {
-                self.burn(amount, msg_sender).map_err(|err: Error| ink::env::debug_println!("{:?}", err)).ok();
-                //balance + amount - fee
-                self.balances.insert(msg_sender, &(self.balance_of(msg_sender) as u128 - amount as u128 - fee_to_take as u128));
-                self.weekly_fee_to_burn += &fee_to_take;
+            if now >= self.end {
+                return self.floor_price;
             }
-            Ok(())
+            let (elapsed, duration) = ((now - self.start) as u128, (self.end - self.start) as u128);
+            //start < now < end, so this can't fail
+            let drop = mul_div_floor(self.start_price - self.floor_price, elapsed, duration).unwrap_or_default();
+            self.start_price - drop
         }
 
-        #[ink(message)]
-        pub fn burn(&mut self, value: u128, address: AccountId) -> Result<()> {
-            self.total_supply = self.total_supply - value;
-            self.balances.insert(address, &(self.balance_of(address) - value));
-            Ok(())
+        fn is_active(&self, now: u64) -> bool {
+            self.start <= now && now < self.end && self.sold < self.supply
         }
 
-        //// Modifies the code which is used to execute calls to this contract address (`AccountId`).
-        ////
-        //// We use this to upgrade the contract logic. We don't do any authorization here, any caller
-        //// can execute this method. In a production contract you would do some authorization here.
-        #[ink(message)]
-        pub fn set_code(&mut self, code_hash: [u8; 32]) {
-            ink::env::set_code_hash(&code_hash).unwrap_or_else(|err| {
-                panic!(
-                    "Failed to `set_code_hash` to {:?} due to {:?}",
-                    code_hash, err
-                )
-            });
-            ink::env::debug_println!("Switched code hash to {:?}.", code_hash);
+        fn clearing_price(&self, now: u64) -> Option<u128> {
+            match self.sold_out_price {
+                Some(price) => Some(price),
+                None if now >= self.end => Some(self.floor_price),
+                None => None,
+            }
         }
     }
 
-    #[cfg(test)]
-    mod tests {    
-        use super::*;
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct AuctionContribution {
+        pub auction: u32,
+        pub paid: Balance,
+        pub bought: Balance,
+    }
 
-        fn default_accounts() -> ink::env::test::DefaultAccounts<Environment> {
-            ink::env::test::default_accounts::<Environment>()
-        }
+    //leaves are blake2(account, max_allocation, price), price per whole token
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct Presale {
+        pub id: u32,
+        pub merkle_root: [u8; 32],
+        pub start: u64,
+        pub end: u64,
+        //purchases vest from `end` when set
+        pub vesting: Option<VestingTerms>,
+    }
 
-        fn alice() -> AccountId {
-            default_accounts().alice
-        }
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct VestingSchedule {
+        pub total: Balance,
+        pub start: u64,
+        pub cliff: u64,
+        pub duration: u64,
+    }
 
-        fn bob() -> AccountId {
-            default_accounts().bob
+    impl VestingSchedule {
+        fn locked_at(&self, now: u64) -> Balance {
+            let elapsed = now.saturating_sub(self.start);
+            if elapsed < self.cliff {
+                return self.total;
+            }
+            if elapsed >= self.duration {
+                return 0;
+            }
+            //0 < elapsed < duration, so this can't fail
+            let vested = mul_div_floor(self.total, elapsed as u128, self.duration as u128).unwrap_or_default();
+            self.total - vested
         }
+    }
 
-        #[ink::test]
+    //an allowance of this size is never decreased by `transfer_from`
+    pub const UNLIMITED_ALLOWANCE: Balance = Balance::MAX;
+
+    #[ink(event)]
+    pub struct Transfer {
+        #[ink(topic)]
+        from: Option<AccountId>,
+        #[ink(topic)]
+        to: AccountId, 
+        value: Balance
+    }
+
+    #[ink(event)]
+    pub struct Approval {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        spender: AccountId,
+        value: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Sold {
+        #[ink(topic)]
+        seller: AccountId,
+        amount: Balance,
+        price: u128,
+        fee: Balance,
+        payout: Balance,
+    }
+
+    #[ink(event)]
+    pub struct FeeParametersChanged {
+        fees: FeeSchedule,
+    }
+
+    #[ink(event)]
+    pub struct FeeExemptionChanged {
+        #[ink(topic)]
+        account: AccountId,
+        exempt: bool,
+    }
+
+    #[ink(event)]
+    pub struct RewardsClaimed {
+        #[ink(topic)]
+        account: AccountId,
+        value: Balance,
+    }
+
+    #[ink(event)]
+    pub struct BurnEpochFinalized {
+        #[ink(topic)]
+        epoch: u64,
+        burned: Balance,
+        #[ink(topic)]
+        keeper: AccountId,
+        incentive: Balance,
+    }
+
+    #[ink(event)]
+    pub struct BuybackExecuted {
+        #[ink(topic)]
+        pool: AccountId,
+        #[ink(topic)]
+        epoch: u64,
+        native_amount: Balance,
+        tokens_burned: Balance,
+        price: u128,
+    }
+
+    #[ink(event)]
+    pub struct TreasurySpendProposed {
+        #[ink(topic)]
+        proposal: u32,
+        #[ink(topic)]
+        proposer: AccountId,
+        to: AccountId,
+        amount: Balance,
+        deadline: u64,
+    }
+
+    #[ink(event)]
+    pub struct TreasurySpendApproved {
+        #[ink(topic)]
+        proposal: u32,
+        #[ink(topic)]
+        account: AccountId,
+        weight: Balance,
+    }
+
+    #[ink(event)]
+    pub struct TreasurySpent {
+        #[ink(topic)]
+        to: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct MaxSupplyChangeProposed {
+        max_supply: Balance,
+        executable_at: u64,
+    }
+
+    #[ink(event)]
+    pub struct MaxSupplyChanged {
+        max_supply: Balance,
+    }
+
+    #[ink(event)]
+    pub struct AirdropCreated {
+        #[ink(topic)]
+        campaign: u32,
+        merkle_root: [u8; 32],
+        total: Balance,
+        expiry: u64,
+    }
+
+    #[ink(event)]
+    pub struct AirdropClaimed {
+        #[ink(topic)]
+        campaign: u32,
+        index: u64,
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct AirdropSwept {
+        #[ink(topic)]
+        campaign: u32,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct AuctionStarted {
+        #[ink(topic)]
+        auction: u32,
+        start: u64,
+        end: u64,
+        start_price: u128,
+        floor_price: u128,
+        supply: Balance,
+    }
+
+    #[ink(event)]
+    pub struct AuctionRefunded {
+        #[ink(topic)]
+        auction: u32,
+        #[ink(topic)]
+        buyer: AccountId,
+        clearing_price: u128,
+        refund: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Staked {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Unstaked {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+        unlock_at: u64,
+    }
+
+    #[ink(event)]
+    pub struct StakingRewardsClaimed {
+        #[ink(topic)]
+        account: AccountId,
+        value: Balance,
+    }
+
+    #[ink(event)]
+    pub struct LockSet {
+        #[ink(topic)]
+        account: AccountId,
+        id: LockIdentifier,
+        amount: Balance,
+        until: u64,
+    }
+
+    #[ink(event)]
+    pub struct LockRemoved {
+        #[ink(topic)]
+        account: AccountId,
+        id: LockIdentifier,
+    }
+
+    #[ink(event)]
+    pub struct Reserved {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Unreserved {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Slashed {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+        burned: bool,
+    }
+
+    #[ink(event)]
+    pub struct PresaleStarted {
+        #[ink(topic)]
+        presale: u32,
+        merkle_root: [u8; 32],
+        start: u64,
+        end: u64,
+    }
+
+    #[ink(event)]
+    pub struct PresalePurchase {
+        #[ink(topic)]
+        buyer: AccountId,
+        amount: Balance,
+        price: u128,
+        vested: bool,
+    }
+
+    #[ink(event)]
+    pub struct ProposalDepositSettled {
+        #[ink(topic)]
+        initiator: AccountId,
+        deposit: Balance,
+        votes: Balance,
+        refunded: bool,
+    }
+
+    #[ink(event)]
+    pub struct BlocklistChanged {
+        #[ink(topic)]
+        account: AccountId,
+        blocked: bool,
+    }
+
+    #[ink(event)]
+    pub struct AllowlistChanged {
+        #[ink(topic)]
+        account: AccountId,
+        allowed: bool,
+    }
+
+    #[ink(event)]
+    pub struct AllowlistModeChanged {
+        enabled: bool,
+    }
+
+    #[ink(event)]
+    pub struct VotingStartedTime {
+        #[ink(topic)]
+        voting_begin_time: u64,
+        voting_number: u32
+    }
+
+    impl Erc20 {
+        #[ink(constructor)]
+        pub fn new(total_supply: Balance) -> Self {
+            let mut balances = Mapping::default();
+            let caller = Self::env().caller();
+            balances.insert(caller, &total_supply);
+            Self::env().emit_event(Transfer {
+                from: None,
+                to: caller,
+                value: total_supply,
+            });
+            Self { 
+                total_supply, 
+                balances,             
+                allowances: Mapping::default(),
+                is_voting_happening: false,
+                votes: Mapping::default(),
+                time_to_vote: 86400,
+                voting_begin_time: Default::default(),
+                voting_end_time: Default::default(),
+                already_voted: Mapping::default(),
+                current_winner: Default::default(),
+                voting_number: Default::default(),
+                token_price: 5,
+                fees: FeeSchedule {
+                    buy: Permill::from_percent(1),
+                    sell: Permill::from_percent(1),
+                    transfer: Permill::from_parts(0),
+                    tiers: Vec::new(),
+                    split: FeeSplit {
+                        burn: Permill::from_percent(100),
+                        treasury: Permill::from_parts(0),
+                        holders: Permill::from_parts(0)
+                    }
+                },
+                fee_exempt: Mapping::default(),
+                fee_to_burn: Default::default(),
+                burn_epoch_length: 604800,
+                burn_epoch_start: Self::env().block_timestamp(),
+                next_burn_epoch: Default::default(),
+                burn_history: Mapping::default(),
+                keeper_incentive: Permill::from_percent(1),
+                buyback_pool: None,
+                buyback_cap_per_epoch: Default::default(),
+                buyback_spent: Default::default(),
+                min_reserve_ratio: Permill::from_percent(100),
+                treasury_quorum: Permill::from_percent(50),
+                spend_proposals: Mapping::default(),
+                next_spend_proposal: 0,
+                spend_approved: Mapping::default(),
+                nonces: Mapping::default(),
+                admin: caller,
+                proposal_threshold: Permill::from_percent(10),
+                voting_threshold: Permill::from_percent(5),
+                pricing_curve: PricingCurve::Flat,
+                decimals: 0,
+                treasury: caller,
+                reward_per_token: Default::default(),
+                reward_per_token_paid: Mapping::default(),
+                pending_rewards: Mapping::default(),
+                reward_pool: Default::default(),
+                max_supply: Balance::MAX,
+                pending_max_supply: None,
+                presale: None,
+                presale_purchased: Mapping::default(),
+                vesting: Mapping::default(),
+                airdrops: Mapping::default(),
+                next_airdrop: 0,
+                airdrop_claimed: Mapping::default(),
+                auction: None,
+                auction_clearing_prices: Mapping::default(),
+                auction_contributions: Mapping::default(),
+                auction_refunds_owed: 0,
+                rate_limits: RateLimits::default(),
+                trade_windows: Mapping::default(),
+                block_volume: (0, 0),
+                total_staked: 0,
+                stakes: Mapping::default(),
+                unbonding: Mapping::default(),
+                emission_rate: 0,
+                unbonding_period: DEFAULT_UNBONDING_PERIOD,
+                staking_reward_per_share: 0,
+                staking_updated_at: 0,
+                locks: Mapping::default(),
+                lock_managers: Mapping::default(),
+                reserved: Mapping::default(),
+                total_reserved: 0,
+                proposal_params: ProposalParams::default(),
+                proposal_deposit: None,
+                round_votes: 0,
+                last_initiated: Mapping::default(),
+                compliance: caller,
+                blocklist: Mapping::default(),
+                allowlist: Mapping::default(),
+                allowlist_only: false
+            }
+        }
+
+        #[ink(constructor)]
+        pub fn new_with_curve(total_supply: Balance, pricing_curve: PricingCurve) -> Self {
+            let mut contract = Self::new(total_supply);
+            contract.pricing_curve = pricing_curve;
+            contract
+        }
+
+        //prices, curve parameters and vote options are native units per whole token (10^decimals units)
+        #[ink(constructor)]
+        pub fn new_with_pricing(total_supply: Balance, decimals: u8, token_price: u128, pricing_curve: PricingCurve, max_supply: Balance) -> Result<Self> {
+            if 10u128.checked_pow(decimals as u32).is_none() {
+                return Err(Error::InvalidDecimals);
+            }
+            if total_supply > max_supply {
+                return Err(Error::CapExceeded);
+            }
+            let mut contract = Self::new_with_curve(total_supply, pricing_curve);
+            contract.decimals = decimals;
+            contract.token_price = token_price;
+            contract.max_supply = max_supply;
+            Ok(contract)
+        }
+
+        #[ink(message)]
+        pub fn decimals(&self) -> u8 {
+            self.decimals
+        }
+
+        #[ink(message)]
+        pub fn total_supply(&self) -> Balance {
+            self.total_supply
+        }
+
+        #[ink(message)]
+        pub fn balance_of(&self, owner: AccountId) -> Balance {
+            self.balances.get(owner).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn allowances(&self, token_owner: AccountId, spender: AccountId) -> Balance {
+            self.allowances.get(&(token_owner, spender)).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn get_current_timestemp(&self) -> u64 {
+            self.env().block_timestamp()
+        }
+
+        #[ink(message)]
+        pub fn get_current_block_number(&self) -> u32 {
+            self.env().block_number()
+        }
+
+        #[ink(message)]
+        pub fn transfer(&mut self, to: AccountId, tokens: u128) -> Result<()>{
+            let msg_sender: AccountId = self.env().caller();
+            let msg_sender_balance: Balance = self.balance_of(msg_sender);
+            if msg_sender_balance < tokens {
+                return Err(Error::InsufficientBalance);
+            } 
+            self.ensure_compliant(Some(msg_sender), Some(to))?;
+            self.ensure_spendable(msg_sender, tokens)?;
+            self.transfer_with_fee(msg_sender, msg_sender, to, tokens)
+        }
+
+        #[ink(message)]
+        pub fn transfer_from(&mut self, from: AccountId, to: AccountId, tokens: Balance) -> Result<()> {
+            let msg_sender_balance = self.balance_of(from);
+            if msg_sender_balance < tokens {
+                return Err(Error::InsufficientBalance);
+            }
+            //the allowance belongs to whoever moves the tokens, not to the recipient
+            let spender = self.env().caller();
+            let allowance = self.allowances(from, spender);
+            if allowance < tokens {
+                return Err(Error::InsufficientAllowance)
+            }
+            self.ensure_compliant(Some(from), Some(to))?;
+            self.ensure_spendable(from, tokens)?;
+
+            if allowance != UNLIMITED_ALLOWANCE {
+                self.allowances.insert((from, spender), &safe_sub(allowance, tokens)?);
+            }
+            self.transfer_with_fee(spender, from, to, tokens)
+        }
+
+        //the recipient gets the tokens minus the transfer fee
+        fn transfer_with_fee(&mut self, operator: AccountId, from: AccountId, to: AccountId, tokens: Balance) -> Result<()> {
+            let fee_to_take = if self.is_fee_exempt(from) || self.is_fee_exempt(to) {
+                0
+            } else {
+                self.fees.fee_for(FeeKind::Transfer, tokens)
+            };
+            let received = safe_sub(tokens, fee_to_take)?;
+            self.move_balance(from, to, received)?;
+            if fee_to_take > 0 {
+                self.move_balance(from, self.fee_pool(), fee_to_take)?;
+                self.accrue_fee(fee_to_take)?;
+            }
+            self.do_safe_transfer_check(operator, from, to, received)
+        }
+
+        //the owner's balance is only checked when the allowance is spent
+        #[ink(message)]
+        pub fn approve(&mut self, spender: AccountId, tokens: u128) -> Result<()>{
+            let msg_sender: AccountId = self.env().caller();
+            self.set_allowance(msg_sender, spender, tokens);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn increase_allowance(&mut self, spender: AccountId, delta: Balance) -> Result<()> {
+            let msg_sender: AccountId = self.env().caller();
+            let allowance = self.allowances(msg_sender, spender).saturating_add(delta);
+            self.set_allowance(msg_sender, spender, allowance);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn decrease_allowance(&mut self, spender: AccountId, delta: Balance) -> Result<()> {
+            let msg_sender: AccountId = self.env().caller();
+            let allowance = safe_sub(self.allowances(msg_sender, spender), delta)
+                .map_err(|_| Error::InsufficientAllowance)?;
+            self.set_allowance(msg_sender, spender, allowance);
+            Ok(())
+        }
+
+        fn set_allowance(&mut self, owner: AccountId, spender: AccountId, tokens: Balance) {
+            self.allowances.insert((owner, spender), &tokens);
+            self.env().emit_event(Approval {
+                owner,
+                spender,
+                value: tokens
+            });
+        }
+
+        #[ink(message)]
+        pub fn nonces(&self, owner: AccountId) -> u64 {
+            self.nonces.get(owner).unwrap_or_default()
+        }
+
+        //binds permits to this contract so a signature can't be replayed on another deployment
+        #[ink(message)]
+        pub fn domain_separator(&self) -> [u8; 32] {
+            let mut output = [0u8; 32];
+            ink::env::hash_encoded::<Blake2x256, _>(&(b"Erc20Permit", self.env().account_id()), &mut output);
+            output
+        }
+
+        //approval signed off-chain by the owner's ecdsa key, anyone can submit it
+        #[ink(message)]
+        pub fn permit(&mut self, owner: AccountId, spender: AccountId, tokens: Balance, deadline: u64, signature: [u8; 65]) -> Result<()> {
+            if self.env().block_timestamp() > deadline {
+                return Err(Error::PermitExpired);
+            }
+            let nonce = self.nonces(owner);
+            let mut message_hash = [0u8; 32];
+            ink::env::hash_encoded::<Blake2x256, _>(
+                &(self.domain_separator(), owner, spender, tokens, nonce, deadline),
+                &mut message_hash,
+            );
+            let mut public_key = [0u8; 33];
+            ink::env::ecdsa_recover(&signature, &message_hash, &mut public_key)
+                .map_err(|_| Error::InvalidSignature)?;
+            //ecdsa accounts are the blake2 hash of the compressed public key
+            let mut signer = [0u8; 32];
+            ink::env::hash_bytes::<Blake2x256>(&public_key, &mut signer);
+            if AccountId::from(signer) != owner {
+                return Err(Error::InvalidSignature);
+            }
+
+            self.nonces.insert(owner, &nonce.checked_add(1).ok_or(Error::Overflow)?);
+            self.set_allowance(owner, spender, tokens);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn admin(&self) -> AccountId {
+            self.admin
+        }
+
+        #[ink(message)]
+        pub fn voting_thresholds(&self) -> (Permill, Permill) {
+            (self.proposal_threshold, self.voting_threshold)
+        }
+
+        //share of the supply needed to start a round and to vote in it
+        #[ink(message)]
+        pub fn set_voting_thresholds(&mut self, proposal_threshold: Permill, voting_threshold: Permill) -> Result<()> {
+            self.ensure_admin()?;
+            if self.is_voting_happening {
+                return Err(Error::VotingIsAlreadyOngoing);
+            }
+            self.proposal_threshold = proposal_threshold;
+            self.voting_threshold = voting_threshold;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn proposal_params(&self) -> ProposalParams {
+            self.proposal_params
+        }
+
+        #[ink(message)]
+        pub fn set_proposal_params(&mut self, proposal_params: ProposalParams) -> Result<()> {
+            self.ensure_admin()?;
+            if self.is_voting_happening {
+                return Err(Error::VotingIsAlreadyOngoing);
+            }
+            self.proposal_params = proposal_params;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn initiate_voting(&mut self, option: u128) -> Result<()>{
+            let caller: AccountId = self.env().caller();
+            if self.voting_power(caller) < self.proposal_threshold.mul_ceil(self.total_supply)? {
+                return Err(Error::InsufficientBalance);
+            } else if self.is_voting_happening || self.get_current_timestemp() < self.voting_end_time {
+                return Err(Error::VotingIsAlreadyOngoing);
+            }
+            if let Some(last) = self.last_initiated.get(caller) {
+                let available_at = last.saturating_add(self.proposal_params.cooldown);
+                if self.get_current_timestemp() < available_at {
+                    return Err(Error::ProposalCooldown(available_at));
+                }
+            }
+            //the deposit doesn't count toward the initiator's own vote
+            let deposit = self.proposal_params.deposit;
+            if self.voting_power(caller).saturating_sub(deposit) < self.voting_threshold.mul_ceil(self.total_supply)? {
+                return Err(Error::InsufficientBalance);
+            }
+            if deposit > 0 {
+                self.reserve_from(caller, deposit)?;
+            }
+            self.proposal_deposit = Some((caller, deposit));
+            self.last_initiated.insert(caller, &self.get_current_timestemp());
+            self.round_votes = 0;
+            self.voting_begin_time = self.get_current_timestemp();
+            self.voting_end_time = self.get_current_timestemp().checked_add(self.time_to_vote).ok_or(Error::Overflow)?;
+            self.is_voting_happening = true;
+            //votes are keyed by round so nothing carries over from the previous one
+            self.voting_number = self.voting_number.checked_add(1).ok_or(Error::Overflow)?;
+            self.vote(option)?;
+            self.env().emit_event(VotingStartedTime{
+                voting_begin_time: self.voting_begin_time,
+                voting_number: self.voting_number
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn vote(&mut self, price: u128) -> Result<()>{
+            let msg_sender: AccountId = self.env().caller();
+            if !self.is_voting_happening ||
+                self.env().block_timestamp() > self.voting_end_time || 
+                self.env().block_timestamp() < self.voting_begin_time
+            {
+                return Err(Error::VotingIsNotOngoing);
+            } else if self.voting_power(msg_sender) < self.voting_threshold.mul_ceil(self.total_supply)? {
+                return Err(Error::InsufficientBalance);
+            } else if self.already_voted.get((self.voting_number, msg_sender)).unwrap_or(false) {
+                return Err(Error::AccountAlreadyVoted);
+            }
+
+            let power = self.voting_power(msg_sender);
+            let tally = safe_add(self.votes_for(price), power)?;
+            self.votes.insert((self.voting_number, price), &tally);
+            self.round_votes = safe_add(self.round_votes, power)?;
+            self.already_voted.insert((self.voting_number, msg_sender), &true);
+            //vote() still counts at voting_end_time itself
+            self.set_lock_for(VOTE_LOCK, msg_sender, self.balance_of(msg_sender), self.voting_end_time.saturating_add(1))?;
+            if self.votes_for(self.current_winner) == 0 {
+                self.current_winner = price;
+            } else if self.votes_for(self.current_winner) < tally {
+                self.current_winner = price;
+            }
+            Ok(())
+        }
+
+        //votes cast for a price in the current round
+        #[ink(message)]
+        pub fn votes_for(&self, price: u128) -> Balance {
+            self.votes.get((self.voting_number, price)).unwrap_or(0)
+        }
+
+        #[ink(message)]
+        pub fn end_voting(&mut self) -> Result<()> {
+            if self.env().block_timestamp() < self.voting_end_time {
+                return Err(Error::TimeToVoteNotElapsed);
+            } else if self.env().block_timestamp() < self.voting_begin_time || !self.is_voting_happening {
+                return Err(Error::VotingIsNotOngoing);
+            }
+            //a round without votes or quorum keeps the current price
+            if self.settle_proposal_deposit()? {
+                self.token_price = self.current_winner;
+                self.pricing_curve = self.pricing_curve.with_base_price(self.current_winner);
+            }
+            //setting all to default
+            self.is_voting_happening = false;
+            self.voting_begin_time = Default::default();
+            self.voting_end_time = Default::default();
+//add token price to the constructor
+            Ok(())
+        }
+
+        //refunds the initiator's deposit if the round reached quorum, burns it otherwise, and tells which
+        fn settle_proposal_deposit(&mut self) -> Result<bool> {
+            let refunded = self.round_votes > 0 && self.round_votes >= self.proposal_params.quorum.mul_ceil(self.total_supply)?;
+            let (initiator, deposit) = match self.proposal_deposit.take() {
+                Some(proposal) => proposal,
+                None => return Ok(refunded),
+            };
+            if refunded {
+                self.unreserve_to(initiator, deposit)?;
+            } else {
+                self.slash_reserved_of(initiator, deposit, true)?;
+            }
+            self.env().emit_event(ProposalDepositSettled {
+                initiator,
+                deposit,
+                votes: self.round_votes,
+                refunded
+            });
+            Ok(refunded)
+        }
+
+        #[ink(message, payable)]
+        pub fn buy(&mut self, amount: u128) -> Result<()>{
+            let msg_sender = self.env().caller();
+            self.ensure_compliant(None, Some(msg_sender))?;
+            if self.presale_active() {
+                return Err(Error::PresaleOngoing);
+            }
+            let cost = self.quote_buy(amount)?;
+            if self.env().transferred_value() == cost {
+                self.consume_rate_limit(msg_sender, TradeKind::Buy, amount)?;
+                self.record_auction_purchase(msg_sender, amount, cost)?;
+                self.mint_bought(msg_sender, amount)?;
+            } else {
+                return Err(Error::ExactAmountOfWeiRequired);
+            }
+            Ok(())
+        }
+
+        //spends the transferred value on as many tokens as it covers and refunds the rest
+        #[ink(message, payable)]
+        pub fn buy_with_value(&mut self, min_tokens_out: Balance) -> Result<Balance> {
+            let msg_sender = self.env().caller();
+            self.ensure_compliant(None, Some(msg_sender))?;
+            if self.presale_active() {
+                return Err(Error::PresaleOngoing);
+            }
+            let value = self.env().transferred_value();
+            let amount = self.tokens_for_value(value)?;
+            //the bound is on what the buyer ends up with, after the buy fee
+            let received = safe_sub(amount, self.trade_fee(FeeKind::Buy, msg_sender, amount))?;
+            if received < min_tokens_out {
+                return Err(Error::SlippageExceeded);
+            }
+            let cost = self.quote_buy(amount)?;
+            let dust = safe_sub(value, cost)?;
+            self.consume_rate_limit(msg_sender, TradeKind::Buy, amount)?;
+            self.record_auction_purchase(msg_sender, amount, cost)?;
+            self.mint_bought(msg_sender, amount)?;
+            if dust > 0 {
+                self.env().transfer(msg_sender, dust).map_err(|_| Error::NativeTransferFailed)?;
+            }
+            Ok(received)
+        }
+
+        //largest amount whose `quote_buy` fits into the value
+        #[ink(message)]
+        pub fn tokens_for_value(&self, value: Balance) -> Result<Balance> {
+            //prices never fall as supply grows, so the spot price bounds the amount
+            let price = match self.auction_price() {
+                Some(price) => price,
+                None => self.spot_price()?,
+            };
+            let mut high = match value.checked_mul(self.token_unit()).and_then(|value| value.checked_div(price)) {
+                Some(amount) => amount.min(self.remaining_mintable()),
+                None => self.remaining_mintable(),
+            };
+            let mut low = 0;
+            while low < high {
+                let middle = high - (high - low) / 2;
+                match self.quote_buy(middle) {
+                    Ok(cost) if cost <= value => low = middle,
+                    _ => high = middle - 1,
+                }
+            }
+            Ok(low)
+        }
+
+        //the whole amount is minted, the fee part goes to the pool
+        fn mint_bought(&mut self, buyer: AccountId, amount: Balance) -> Result<()> {
+            //fee included, so the buyer's part can't fit while the fee doesn't
+            if amount > self.remaining_mintable() {
+                return Err(Error::CapExceeded);
+            }
+            let fee_to_take = self.trade_fee(FeeKind::Buy, buyer, amount);
+            self.mint_to(buyer, safe_sub(amount, fee_to_take)?)?;
+            if fee_to_take > 0 {
+                self.mint_to(self.fee_pool(), fee_to_take)?;
+            }
+            self.accrue_fee(fee_to_take)
+        }
+
+        fn trade_fee(&self, kind: FeeKind, trader: AccountId, amount: Balance) -> Balance {
+            if self.is_fee_exempt(trader) {
+                return 0;
+            }
+            self.fees.fee_for(kind, amount)
+        }
+
+        //every fee that reaches the pool is split here between burn, treasury and holders
+        fn accrue_fee(&mut self, fee: Balance) -> Result<()> {
+            let split = self.fees.split;
+            let to_treasury = split.treasury.mul_floor(fee);
+            if to_treasury > 0 {
+                self.move_balance(self.fee_pool(), self.treasury, to_treasury)?;
+            }
+            //holders are everyone but the pool itself, reserved tokens don't earn
+            let eligible_supply = safe_sub(safe_sub(self.total_supply, self.balance_of(self.fee_pool()))?, self.total_reserved)?;
+            let mut to_holders = split.holders.mul_floor(fee);
+            if eligible_supply == 0 {
+                to_holders = 0;
+            }
+            if to_holders > 0 {
+                let per_token = mul_div_floor(to_holders, REWARD_PRECISION, eligible_supply)?;
+                self.reward_per_token = safe_add(self.reward_per_token, per_token)?;
+                //what per_token rounds off can never be claimed, so it is burned with the rest
+                to_holders = reward_for(eligible_supply, per_token)?;
+                self.reward_pool = safe_add(self.reward_pool, to_holders)?;
+            }
+            let to_burn = safe_sub(safe_sub(fee, to_treasury)?, to_holders)?;
+            self.fee_to_burn = safe_add(self.fee_to_burn, to_burn)?;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn treasury(&self) -> AccountId {
+            self.treasury
+        }
+
+        #[ink(message)]
+        pub fn set_treasury(&mut self, treasury: AccountId) -> Result<()> {
+            self.ensure_admin()?;
+            self.treasury = treasury;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn claimable_rewards(&self, account: AccountId) -> Balance {
+            self.pending_rewards.get(account).unwrap_or_default()
+                .saturating_add(self.earned_since_checkpoint(account).unwrap_or_default())
+        }
+
+        #[ink(message)]
+        pub fn claim_rewards(&mut self) -> Result<Balance> {
+            let msg_sender = self.env().caller();
+            self.ensure_compliant(None, Some(msg_sender))?;
+            self.update_rewards(msg_sender)?;
+            let value = self.pending_rewards.get(msg_sender).unwrap_or_default();
+            if value > 0 {
+                self.pending_rewards.insert(msg_sender, &0);
+                self.reward_pool = safe_sub(self.reward_pool, value)?;
+                self.move_balance(self.fee_pool(), msg_sender, value)?;
+                self.env().emit_event(RewardsClaimed {
+                    account: msg_sender,
+                    value
+                });
+            }
+            Ok(value)
+        }
+
+        fn earned_since_checkpoint(&self, account: AccountId) -> Result<Balance> {
+            if account == self.fee_pool() {
+                return Ok(0);
+            }
+            let paid = self.reward_per_token_paid.get(account).unwrap_or_default();
+            reward_for(self.balance_of(account), safe_sub(self.reward_per_token, paid)?)
+        }
+
+        //has to run before every balance change of the account
+        fn update_rewards(&mut self, account: AccountId) -> Result<()> {
+            let earned = self.earned_since_checkpoint(account)?;
+            if earned > 0 {
+                let pending = safe_add(self.pending_rewards.get(account).unwrap_or_default(), earned)?;
+                self.pending_rewards.insert(account, &pending);
+            }
+            if self.reward_per_token_paid.get(account).unwrap_or_default() != self.reward_per_token {
+                self.reward_per_token_paid.insert(account, &self.reward_per_token);
+            }
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn fees(&self) -> FeeSchedule {
+            self.fees.clone()
+        }
+
+        #[ink(message)]
+        pub fn set_fees(&mut self, fees: FeeSchedule) -> Result<()> {
+            self.ensure_admin()?;
+            fees.validate()?;
+            self.fees = fees.clone();
+            self.env().emit_event(FeeParametersChanged { fees });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn is_fee_exempt(&self, account: AccountId) -> bool {
+            self.fee_exempt.get(account).unwrap_or_default()
+        }
+
+        //e.g. the treasury or market makers
+        #[ink(message)]
+        pub fn set_fee_exempt(&mut self, account: AccountId, exempt: bool) -> Result<()> {
+            self.ensure_admin()?;
+            self.fee_exempt.insert(account, &exempt);
+            self.env().emit_event(FeeExemptionChanged { account, exempt });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn pricing_curve(&self) -> PricingCurve {
+            self.pricing_curve
+        }
+
+        #[ink(message)]
+        pub fn spot_price(&self) -> Result<u128> {
+            self.pricing_curve.spot_price(self.token_price, self.total_supply)
+        }
+
+        //native value `buy(amount)` expects, at the auction price while one runs
+        #[ink(message)]
+        pub fn quote_buy(&self, amount: Balance) -> Result<Balance> {
+            if let Some(auction) = self.auction.filter(|auction| auction.is_active(self.env().block_timestamp())) {
+                if safe_add(auction.sold, amount)? > auction.supply {
+                    return Err(Error::AuctionSupplyExceeded);
+                }
+                let price = auction.price_at(self.env().block_timestamp());
+                return Ok(safe_mul(amount, price)?.div_ceil(self.token_unit()));
+            }
+            let cost = self.pricing_curve.cost(self.token_price, self.total_supply, safe_add(self.total_supply, amount)?)?;
+            //buyers pay any fraction of a native unit
+            Ok(cost.div_ceil(self.token_unit()))
+        }
+
+        //native value `sell(amount)` pays out
+        #[ink(message)]
+        pub fn quote_sell(&self, amount: Balance) -> Result<Balance> {
+            let payout = self.pricing_curve.cost(self.token_price, safe_sub(self.total_supply, amount)?, self.total_supply)?;
+            Ok(payout / self.token_unit())
+        }
+
+        fn token_unit(&self) -> u128 {
+            10u128.pow(self.decimals as u32)
+        }
+
+        //closes every elapsed epoch at once, burns the pooled fees and pays the caller for doing it
+        #[ink(message)]
+        pub fn finalize_burn_epoch(&mut self) -> Result<u64> {
+            let elapsed = self.env().block_timestamp().saturating_sub(self.burn_epoch_start);
+            let completed = elapsed / self.burn_epoch_length;
+            if completed == 0 {
+                return Err(Error::TimeForFeeBurnHasNotLapsed);
+            }
+            let next_burn_epoch = self.next_burn_epoch.checked_add(completed).ok_or(Error::Overflow)?;
+            let epoch = next_burn_epoch.checked_sub(1).ok_or(Error::Underflow)?;
+            let burn_epoch_start = completed.checked_mul(self.burn_epoch_length)
+                .and_then(|elapsed| self.burn_epoch_start.checked_add(elapsed))
+                .ok_or(Error::Overflow)?;
+            let keeper = self.env().caller();
+            let incentive = self.keeper_incentive.mul_floor(self.fee_to_burn);
+            let burned = safe_sub(self.fee_to_burn, incentive)?;
+
+            if incentive > 0 {
+                self.ensure_compliant(None, Some(keeper))?;
+                self.move_balance(self.fee_pool(), keeper, incentive)?;
+            }
+            self.burn_from(self.fee_pool(), burned)?;
+            self.fee_to_burn = 0;
+            self.burn_history.insert(epoch, &burned);
+            self.next_burn_epoch = next_burn_epoch;
+            self.burn_epoch_start = burn_epoch_start;
+            self.env().emit_event(BurnEpochFinalized {
+                epoch,
+                burned,
+                keeper,
+                incentive
+            });
+            Ok(epoch)
+        }
+
+        #[ink(message)]
+        pub fn fee_to_burn(&self) -> Balance {
+            self.fee_to_burn
+        }
+
+        //amount burned when the epoch was finalized, epochs skipped by a late finalization burn nothing
+        #[ink(message)]
+        pub fn burn_history(&self, epoch: u64) -> Option<Balance> {
+            self.burn_history.get(epoch)
+        }
+
+        //first epoch that has not been finalized and the time it started
+        #[ink(message)]
+        pub fn current_burn_epoch(&self) -> (u64, u64) {
+            (self.next_burn_epoch, self.burn_epoch_start)
+        }
+
+        //epoch the current block falls into, finalized or not
+        fn current_epoch(&self) -> u64 {
+            let elapsed = self.env().block_timestamp().saturating_sub(self.burn_epoch_start);
+            self.next_burn_epoch + elapsed / self.burn_epoch_length
+        }
+
+        //pays native currency to the sell-side pool for its tokens at the spot price and burns them;
+        //the pool opts in by approving the contract account
+        #[ink(message)]
+        pub fn buyback(&mut self, native_amount: Balance) -> Result<Balance> {
+            if self.env().caller() != self.treasury {
+                return Err(Error::NotTreasury);
+            }
+            let pool = self.buyback_pool.ok_or(Error::BuybackPoolNotSet)?;
+            let price = self.spot_price()?;
+            let tokens = safe_mul(native_amount, self.token_unit())?
+                .checked_div(price)
+                .ok_or(Error::InvalidPrice)?;
+            if tokens == 0 {
+                return Err(Error::BuybackTooSmall);
+            }
+            //only whole token units are bought, the rest of native_amount stays in the treasury
+            let native_amount = mul_div_floor(tokens, price, self.token_unit())?;
+            let epoch = self.current_epoch();
+            let (spent_epoch, spent) = self.buyback_spent;
+            let spent = if spent_epoch == epoch { spent } else { 0 };
+            let spent = safe_add(spent, native_amount)?;
+            if spent > self.buyback_cap_per_epoch {
+                return Err(Error::BuybackCapExceeded);
+            }
+            if self.treasury_surplus()? < native_amount {
+                return Err(Error::ReserveRatioTooLow);
+            }
+            let allowance = self.allowances(pool, self.env().account_id());
+            if allowance < tokens {
+                return Err(Error::InsufficientAllowance);
+            }
+
+            if allowance != UNLIMITED_ALLOWANCE {
+                self.allowances.insert((pool, self.env().account_id()), &(allowance - tokens));
+            }
+            self.burn_from(pool, tokens)?;
+            self.buyback_spent = (epoch, spent);
+            self.env().transfer(pool, native_amount).map_err(|_| Error::NativeTransferFailed)?;
+            self.env().emit_event(BuybackExecuted {
+                pool,
+                epoch,
+                native_amount,
+                tokens_burned: tokens,
+                price
+            });
+            Ok(tokens)
+        }
+
+        #[ink(message)]
+        pub fn buyback_config(&self) -> (Option<AccountId>, Balance) {
+            (self.buyback_pool, self.buyback_cap_per_epoch)
+        }
+
+        //the cap is in native currency per burn epoch
+        #[ink(message)]
+        pub fn set_buyback_config(&mut self, pool: Option<AccountId>, cap_per_epoch: Balance) -> Result<()> {
+            self.ensure_admin()?;
+            self.buyback_pool = pool;
+            self.buyback_cap_per_epoch = cap_per_epoch;
+            Ok(())
+        }
+
+        //native currency held by the contract, reserve and surplus together
+        #[ink(message)]
+        pub fn treasury_balance(&self) -> Balance {
+            self.env().balance()
+        }
+
+        //native value of the whole supply at the spot price
+        fn supply_value(&self) -> Result<Balance> {
+            Ok(safe_mul(self.total_supply, self.spot_price()?)? / self.token_unit())
+        }
+
+        //part of the balance that backs redemptions
+        #[ink(message)]
+        pub fn required_reserve(&self) -> Result<Balance> {
+            self.min_reserve_ratio.mul_ceil(self.supply_value()?)
+        }
+
+        //part of the balance governance may spend
+        #[ink(message)]
+        pub fn treasury_surplus(&self) -> Result<Balance> {
+            Ok(self.treasury_balance().saturating_sub(self.required_reserve()?).saturating_sub(self.auction_refunds_owed))
+        }
+
+        //treasury balance over supply value in parts per million, u128::MAX when nothing is outstanding
+        #[ink(message)]
+        pub fn reserve_ratio(&self) -> Result<u128> {
+            let supply_value = self.supply_value()?;
+            if supply_value == 0 {
+                return Ok(u128::MAX);
+            }
+            Ok(self.treasury_balance().saturating_mul(Permill::ACCURACY as u128) / supply_value)
+        }
+
+        #[ink(message)]
+        pub fn min_reserve_ratio(&self) -> Permill {
+            self.min_reserve_ratio
+        }
+
+        #[ink(message)]
+        pub fn set_min_reserve_ratio(&mut self, min_reserve_ratio: Permill) -> Result<()> {
+            self.ensure_admin()?;
+            self.min_reserve_ratio = min_reserve_ratio;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn treasury_quorum(&self) -> Permill {
+            self.treasury_quorum
+        }
+
+        //share of the supply that has to approve a spend
+        #[ink(message)]
+        pub fn set_treasury_quorum(&mut self, treasury_quorum: Permill) -> Result<()> {
+            self.ensure_admin()?;
+            self.treasury_quorum = treasury_quorum;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn spend_proposal(&self, proposal: u32) -> Option<SpendProposal> {
+            self.spend_proposals.get(proposal)
+        }
+
+        //proposes spending surplus native currency, holders able to start a price vote can propose
+        #[ink(message)]
+        pub fn treasury_spend(&mut self, to: AccountId, amount: Balance) -> Result<u32> {
+            let caller = self.env().caller();
+            if self.voting_power(caller) < self.proposal_threshold.mul_ceil(self.total_supply)? {
+                return Err(Error::InsufficientBalance);
+            }
+            if self.treasury_surplus()? < amount {
+                return Err(Error::ReserveRatioTooLow);
+            }
+            let proposal = self.next_spend_proposal;
+            self.next_spend_proposal = proposal.checked_add(1).ok_or(Error::Overflow)?;
+            let deadline = self.env().block_timestamp().saturating_add(SPEND_APPROVAL_PERIOD);
+            self.spend_proposals.insert(proposal, &SpendProposal { to, amount, approvals: 0, deadline, executed: false });
+            self.env().emit_event(TreasurySpendProposed {
+                proposal,
+                proposer: caller,
+                to,
+                amount,
+                deadline
+            });
+            Ok(proposal)
+        }
+
+        //approves with the caller's voting power, the balance stays locked until the deadline
+        #[ink(message)]
+        pub fn approve_treasury_spend(&mut self, proposal: u32) -> Result<()> {
+            let caller = self.env().caller();
+            let mut spend = self.spend_proposals.get(proposal).ok_or(Error::ProposalNotFound)?;
+            if spend.executed {
+                return Err(Error::ProposalAlreadyExecuted);
+            }
+            if self.env().block_timestamp() >= spend.deadline {
+                return Err(Error::ProposalExpired);
+            }
+            if self.spend_approved.get((proposal, caller)).unwrap_or_default() {
+                return Err(Error::AccountAlreadyVoted);
+            }
+            let weight = self.voting_power(caller);
+            spend.approvals = safe_add(spend.approvals, weight)?;
+            self.spend_proposals.insert(proposal, &spend);
+            self.spend_approved.insert((proposal, caller), &true);
+
+            //one lock covers every open proposal the account approved
+            let held = self.locks_of(caller).into_iter().find(|lock| lock.id == SPEND_LOCK);
+            let amount = held.map_or(0, |lock| lock.amount).max(self.balance_of(caller));
+            let until = held.map_or(0, |lock| lock.until).max(spend.deadline);
+            self.set_lock_for(SPEND_LOCK, caller, amount, until)?;
+            self.env().emit_event(TreasurySpendApproved {
+                proposal,
+                account: caller,
+                weight
+            });
+            Ok(())
+        }
+
+        //anyone can execute an approved spend before its deadline, it still only spends the surplus
+        #[ink(message)]
+        pub fn execute_treasury_spend(&mut self, proposal: u32) -> Result<()> {
+            let mut spend = self.spend_proposals.get(proposal).ok_or(Error::ProposalNotFound)?;
+            if spend.executed {
+                return Err(Error::ProposalAlreadyExecuted);
+            }
+            if self.env().block_timestamp() >= spend.deadline {
+                return Err(Error::ProposalExpired);
+            }
+            if spend.approvals < self.treasury_quorum.mul_ceil(self.total_supply)? {
+                return Err(Error::QuorumNotReached);
+            }
+            if self.treasury_surplus()? < spend.amount {
+                return Err(Error::ReserveRatioTooLow);
+            }
+            spend.executed = true;
+            self.spend_proposals.insert(proposal, &spend);
+            self.env().transfer(spend.to, spend.amount).map_err(|_| Error::NativeTransferFailed)?;
+            self.env().emit_event(TreasurySpent { to: spend.to, amount: spend.amount });
+            Ok(())
+        }
+
+        //the current epoch keeps its start and runs for the new length, so its number doesn't move;
+        //elapsed epochs have to be finalized at the old length first
+        #[ink(message)]
+        pub fn set_burn_schedule(&mut self, burn_epoch_length: u64, keeper_incentive: Permill) -> Result<()> {
+            self.ensure_admin()?;
+            let elapsed = self.env().block_timestamp().saturating_sub(self.burn_epoch_start);
+            if elapsed >= self.burn_epoch_length {
+                return Err(Error::BurnEpochNotFinalized);
+            }
+            if burn_epoch_length == 0 || elapsed >= burn_epoch_length {
+                return Err(Error::InvalidBurnEpochLength);
+            }
+            if keeper_incentive > MAX_FEE {
+                return Err(Error::FeeTooHigh);
+            }
+            self.burn_epoch_length = burn_epoch_length;
+            self.keeper_incentive = keeper_incentive;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn max_supply(&self) -> Balance {
+            self.max_supply
+        }
+
+        #[ink(message)]
+        pub fn remaining_mintable(&self) -> Balance {
+            self.max_supply.saturating_sub(self.total_supply)
+        }
+
+        #[ink(message)]
+        pub fn pending_max_supply(&self) -> Option<(Balance, u64)> {
+            self.pending_max_supply
+        }
+
+        //replaces any pending proposal, applicable after MAX_SUPPLY_TIMELOCK
+        #[ink(message)]
+        pub fn propose_max_supply(&mut self, max_supply: Balance) -> Result<()> {
+            self.ensure_admin()?;
+            let executable_at = self.env().block_timestamp().saturating_add(MAX_SUPPLY_TIMELOCK);
+            self.pending_max_supply = Some((max_supply, executable_at));
+            self.env().emit_event(MaxSupplyChangeProposed { max_supply, executable_at });
+            Ok(())
+        }
+
+        //anyone can apply a proposal once its timelock is over
+        #[ink(message)]
+        pub fn apply_max_supply(&mut self) -> Result<()> {
+            let (max_supply, executable_at) = self.pending_max_supply.ok_or(Error::NoPendingChange)?;
+            if self.env().block_timestamp() < executable_at {
+                return Err(Error::TimelockNotElapsed);
+            }
+            if max_supply < self.total_supply {
+                return Err(Error::CapExceeded);
+            }
+            self.max_supply = max_supply;
+            self.pending_max_supply = None;
+            self.env().emit_event(MaxSupplyChanged { max_supply });
+            Ok(())
+        }
+
+        //new tokens are credited to the admin
+        #[ink(message)]
+        pub fn mint(&mut self, value: u128) -> Result<()> {
+            self.ensure_admin()?;
+            self.ensure_compliant(None, Some(self.admin))?;
+            self.mint_to(self.admin, value)
+        }
+
+        #[ink(message)]
+        pub fn compliance(&self) -> AccountId {
+            self.compliance
+        }
+
+        #[ink(message)]
+        pub fn set_compliance(&mut self, compliance: AccountId) -> Result<()> {
+            self.ensure_admin()?;
+            self.compliance = compliance;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn is_blocked(&self, account: AccountId) -> bool {
+            self.blocklist.get(account).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn is_allowed(&self, account: AccountId) -> bool {
+            self.allowlist.get(account).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn allowlist_only(&self) -> bool {
+            self.allowlist_only
+        }
+
+        //blocked accounts can neither send nor receive
+        #[ink(message)]
+        pub fn set_blocked(&mut self, account: AccountId, blocked: bool) -> Result<()> {
+            self.ensure_compliance()?;
+            self.blocklist.insert(account, &blocked);
+            self.env().emit_event(BlocklistChanged {
+                account,
+                blocked
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_allowed(&mut self, account: AccountId, allowed: bool) -> Result<()> {
+            self.ensure_compliance()?;
+            self.allowlist.insert(account, &allowed);
+            self.env().emit_event(AllowlistChanged {
+                account,
+                allowed
+            });
+            Ok(())
+        }
+
+        //when enabled only allowlisted accounts can receive tokens
+        #[ink(message)]
+        pub fn set_allowlist_only(&mut self, enabled: bool) -> Result<()> {
+            self.ensure_compliance()?;
+            self.allowlist_only = enabled;
+            self.env().emit_event(AllowlistModeChanged {
+                enabled
+            });
+            Ok(())
+        }
+
+        //checked on the user facing side of a move, the fee pool and treasury legs aren't
+        fn ensure_compliant(&self, from: Option<AccountId>, to: Option<AccountId>) -> Result<()> {
+            if from.is_some_and(|from| self.is_blocked(from)) {
+                return Err(Error::SenderBlocked);
+            }
+            if to.is_some_and(|to| self.is_blocked(to) || (self.allowlist_only && !self.is_allowed(to))) {
+                return Err(Error::RecipientNotAllowed);
+            }
+            Ok(())
+        }
+
+        fn ensure_compliance(&self) -> Result<()> {
+            if self.env().caller() != self.compliance {
+                return Err(Error::NotCompliance);
+            }
+            Ok(())
+        }
+
+        //account holding the fees collected by buy and sell until they are burned
+        #[ink(message)]
+        pub fn fee_pool(&self) -> AccountId {
+            self.env().account_id()
+        }
+
+        #[ink(message)]
+        pub fn presale(&self) -> Option<Presale> {
+            self.presale
+        }
+
+        //public `buy` stays closed while the presale window is open
+        #[ink(message)]
+        pub fn presale_active(&self) -> bool {
+            let now = self.env().block_timestamp();
+            self.presale.is_some_and(|presale| presale.start <= now && now < presale.end)
+        }
+
+        //bought in the current presale, every presale starts a fresh allocation
+        #[ink(message)]
+        pub fn presale_purchased(&self, account: AccountId) -> Balance {
+            self.presale.map_or(0, |presale| self.presale_purchased.get((presale.id, account)).unwrap_or_default())
+        }
+
+        #[ink(message)]
+        pub fn start_presale(&mut self, merkle_root: [u8; 32], start: u64, end: u64, vesting: Option<VestingTerms>) -> Result<u32> {
+            self.ensure_admin()?;
+            if end <= start {
+                return Err(Error::InvalidPresale);
+            }
+            if self.presale_active() {
+                return Err(Error::PresaleOngoing);
+            }
+            let id = match self.presale {
+                Some(previous) => previous.id.checked_add(1).ok_or(Error::Overflow)?,
+                None => 0,
+            };
+            self.presale = Some(Presale { id, merkle_root, start, end, vesting });
+            self.env().emit_event(PresaleStarted { presale: id, merkle_root, start, end });
+            Ok(id)
+        }
+
+        //the caller proves its (max_allocation, price) leaf and pays amount at that price
+        #[ink(message, payable)]
+        pub fn presale_buy(&mut self, amount: Balance, max_allocation: Balance, price: u128, proof: Vec<[u8; 32]>) -> Result<()> {
+            let msg_sender = self.env().caller();
+            self.ensure_compliant(None, Some(msg_sender))?;
+            let presale = self.presale.ok_or(Error::PresaleNotActive)?;
+            if !self.presale_active() {
+                return Err(Error::PresaleNotActive);
+            }
+            if !verify_merkle_proof(presale.merkle_root, presale_leaf(msg_sender, max_allocation, price), &proof) {
+                return Err(Error::InvalidProof);
+            }
+            let purchased = safe_add(self.presale_purchased(msg_sender), amount)?;
+            if purchased > max_allocation {
+                return Err(Error::AllocationExceeded);
+            }
+            let cost = safe_mul(amount, price)?.div_ceil(self.token_unit());
+            if self.env().transferred_value() != cost {
+                return Err(Error::ExactAmountOfWeiRequired);
+            }
+
+            if let Some(terms) = presale.vesting {
+                self.add_vesting(msg_sender, amount, presale.end, terms)?;
+            }
+            self.mint_to(msg_sender, amount)?;
+            self.presale_purchased.insert((presale.id, msg_sender), &purchased);
+            self.env().emit_event(PresalePurchase {
+                buyer: msg_sender,
+                amount,
+                price,
+                vested: presale.vesting.is_some()
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn vesting_schedule(&self, account: AccountId) -> Option<VestingSchedule> {
+            self.vesting.get(account)
+        }
+
+        //part of the balance that can't be spent yet, the largest of vesting and active locks
+        #[ink(message)]
+        pub fn locked_balance(&self, account: AccountId) -> Balance {
+            let now = self.env().block_timestamp();
+            let vesting = self.vesting.get(account).map_or(0, |schedule| schedule.locked_at(now));
+            self.locks_of(account).iter().map(|lock| lock.amount).fold(vesting, Balance::max)
+        }
+
+        #[ink(message)]
+        pub fn free_balance(&self, account: AccountId) -> Balance {
+            self.balance_of(account).saturating_sub(self.locked_balance(account))
+        }
+
+        //active locks only
+        #[ink(message)]
+        pub fn locks_of(&self, account: AccountId) -> Vec<BalanceLock> {
+            let now = self.env().block_timestamp();
+            let mut locks = self.locks.get(account).unwrap_or_default();
+            locks.retain(|lock| now < lock.until);
+            locks
+        }
+
+        #[ink(message)]
+        pub fn is_lock_manager(&self, account: AccountId) -> bool {
+            self.lock_managers.get(account).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn set_lock_manager(&mut self, account: AccountId, enabled: bool) -> Result<()> {
+            self.ensure_admin()?;
+            self.lock_managers.insert(account, &enabled);
+            Ok(())
+        }
+
+        //replaces the account's lock with the same id
+        #[ink(message)]
+        pub fn set_lock(&mut self, id: LockIdentifier, account: AccountId, amount: Balance, until: u64) -> Result<()> {
+            self.ensure_lock_manager()?;
+            Self::ensure_external_lock(id)?;
+            self.set_lock_for(id, account, amount, until)
+        }
+
+        #[ink(message)]
+        pub fn remove_lock(&mut self, id: LockIdentifier, account: AccountId) -> Result<()> {
+            self.ensure_lock_manager()?;
+            Self::ensure_external_lock(id)?;
+            self.remove_lock_for(id, account);
+            Ok(())
+        }
+
+        //the vote and spend approval locks are only managed by the contract itself
+        fn ensure_external_lock(id: LockIdentifier) -> Result<()> {
+            if id == VOTE_LOCK || id == SPEND_LOCK {
+                return Err(Error::ReservedLockId);
+            }
+            Ok(())
+        }
+
+        fn set_lock_for(&mut self, id: LockIdentifier, account: AccountId, amount: Balance, until: u64) -> Result<()> {
+            //expired locks are dropped whenever the list is written
+            let mut locks = self.locks_of(account);
+            locks.retain(|lock| lock.id != id);
+            if locks.len() >= MAX_LOCKS {
+                return Err(Error::TooManyLocks);
+            }
+            locks.push(BalanceLock { id, amount, until });
+            self.locks.insert(account, &locks);
+            self.env().emit_event(LockSet {
+                account,
+                id,
+                amount,
+                until
+            });
+            Ok(())
+        }
+
+        fn remove_lock_for(&mut self, id: LockIdentifier, account: AccountId) {
+            let mut locks = self.locks_of(account);
+            locks.retain(|lock| lock.id != id);
+            if locks.is_empty() {
+                self.locks.remove(account);
+            } else {
+                self.locks.insert(account, &locks);
+            }
+            self.env().emit_event(LockRemoved {
+                account,
+                id
+            });
+        }
+
+        //tokens set aside from the balance, they can't move, vote or earn fees until unreserved
+        #[ink(message)]
+        pub fn reserved_balance(&self, account: AccountId) -> Balance {
+            self.reserved.get(account).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn total_reserved(&self) -> Balance {
+            self.total_reserved
+        }
+
+        //lock managers also handle reserves
+        #[ink(message)]
+        pub fn reserve(&mut self, account: AccountId, amount: Balance) -> Result<()> {
+            self.ensure_lock_manager()?;
+            self.reserve_from(account, amount)
+        }
+
+        //returns as much as is reserved, up to amount
+        #[ink(message)]
+        pub fn unreserve(&mut self, account: AccountId, amount: Balance) -> Result<Balance> {
+            self.ensure_lock_manager()?;
+            self.ensure_compliant(None, Some(account))?;
+            self.unreserve_to(account, amount)
+        }
+
+        //slashed tokens go to the treasury, or to the burn pool when burn is set
+        #[ink(message)]
+        pub fn slash_reserved(&mut self, account: AccountId, amount: Balance, burn: bool) -> Result<Balance> {
+            self.ensure_lock_manager()?;
+            self.slash_reserved_of(account, amount, burn)
+        }
+
+        fn reserve_from(&mut self, account: AccountId, amount: Balance) -> Result<()> {
+            if self.balance_of(account) < amount {
+                return Err(Error::InsufficientBalance);
+            }
+            self.ensure_spendable(account, amount)?;
+            self.update_rewards(account)?;
+            self.balances.insert(account, &safe_sub(self.balance_of(account), amount)?);
+            self.reserved.insert(account, &safe_add(self.reserved_balance(account), amount)?);
+            self.total_reserved = safe_add(self.total_reserved, amount)?;
+            self.env().emit_event(Reserved {
+                account,
+                amount
+            });
+            Ok(())
+        }
+
+        fn unreserve_to(&mut self, account: AccountId, amount: Balance) -> Result<Balance> {
+            let amount = self.take_reserved(account, amount)?;
+            self.update_rewards(account)?;
+            self.balances.insert(account, &safe_add(self.balance_of(account), amount)?);
+            self.env().emit_event(Unreserved {
+                account,
+                amount
+            });
+            Ok(amount)
+        }
+
+        fn slash_reserved_of(&mut self, account: AccountId, amount: Balance, burn: bool) -> Result<Balance> {
+            let amount = self.take_reserved(account, amount)?;
+            let to = if burn { self.fee_pool() } else { self.treasury };
+            self.update_rewards(to)?;
+            self.balances.insert(to, &safe_add(self.balance_of(to), amount)?);
+            if burn {
+                self.fee_to_burn = safe_add(self.fee_to_burn, amount)?;
+            }
+            self.env().emit_event(Transfer {
+                from: Some(account),
+                to,
+                value: amount
+            });
+            self.env().emit_event(Slashed {
+                account,
+                amount,
+                burned: burn
+            });
+            Ok(amount)
+        }
+
+        fn take_reserved(&mut self, account: AccountId, amount: Balance) -> Result<Balance> {
+            let reserved = self.reserved_balance(account);
+            let amount = amount.min(reserved);
+            self.reserved.insert(account, &(reserved - amount));
+            self.total_reserved = safe_sub(self.total_reserved, amount)?;
+            Ok(amount)
+        }
+
+        fn ensure_lock_manager(&self) -> Result<()> {
+            let caller = self.env().caller();
+            if caller != self.admin && !self.is_lock_manager(caller) {
+                return Err(Error::NotLockManager);
+            }
+            Ok(())
+        }
+
+        //later purchases join the account's schedule for the same start, a fully vested one makes way for a new start
+        fn add_vesting(&mut self, account: AccountId, amount: Balance, start: u64, terms: VestingTerms) -> Result<()> {
+            let now = self.env().block_timestamp();
+            let schedule = match self.vesting.get(account) {
+                Some(schedule) if schedule.start == start => VestingSchedule { total: safe_add(schedule.total, amount)?, ..schedule },
+                Some(schedule) if schedule.locked_at(now) > 0 => return Err(Error::VestingScheduleMismatch),
+                _ => VestingSchedule { total: amount, start, cliff: terms.cliff, duration: terms.duration },
+            };
+            self.vesting.insert(account, &schedule);
+            Ok(())
+        }
+
+        fn ensure_spendable(&self, account: AccountId, amount: Balance) -> Result<()> {
+            if self.free_balance(account) < amount {
+                return Err(Error::BalanceLocked);
+            }
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn airdrop(&self, campaign: u32) -> Option<Airdrop> {
+            self.airdrops.get(campaign)
+        }
+
+        //funded from the admin's balance, leaves are blake2(index, account, amount)
+        #[ink(message)]
+        pub fn create_airdrop(&mut self, merkle_root: [u8; 32], total: Balance, expiry: u64) -> Result<u32> {
+            self.ensure_admin()?;
+            //a campaign nobody can claim would only be swept back
+            if expiry <= self.env().block_timestamp() {
+                return Err(Error::AirdropExpired);
+            }
+            if self.balance_of(self.admin) < total {
+                return Err(Error::InsufficientBalance);
+            }
+            self.ensure_spendable(self.admin, total)?;
+            self.move_balance(self.admin, self.fee_pool(), total)?;
+            let campaign = self.next_airdrop;
+            self.next_airdrop = campaign.checked_add(1).ok_or(Error::Overflow)?;
+            self.airdrops.insert(campaign, &Airdrop { merkle_root, total, claimed: 0, expiry });
+            self.env().emit_event(AirdropCreated {
+                campaign,
+                merkle_root,
+                total,
+                expiry
+            });
+            Ok(campaign)
+        }
+
+        //one bit per leaf, 256 leaves per storage word
+        #[ink(message)]
+        pub fn is_airdrop_claimed(&self, campaign: u32, index: u64) -> bool {
+            let word = self.airdrop_claimed.get((campaign, index / 256)).unwrap_or_default();
+            let bit = (index % 256) as usize;
+            word[bit / 8] & (1 << (bit % 8)) != 0
+        }
+
+        #[ink(message)]
+        pub fn claim_airdrop(&mut self, campaign: u32, index: u64, amount: Balance, proof: Vec<[u8; 32]>) -> Result<()> {
+            let msg_sender = self.env().caller();
+            self.ensure_compliant(None, Some(msg_sender))?;
+            let mut airdrop = self.airdrops.get(campaign).ok_or(Error::AirdropNotFound)?;
+            if self.env().block_timestamp() >= airdrop.expiry {
+                return Err(Error::AirdropExpired);
+            }
+            if self.is_airdrop_claimed(campaign, index) {
+                return Err(Error::AlreadyClaimed);
+            }
+            if !verify_merkle_proof(airdrop.merkle_root, airdrop_leaf(index, msg_sender, amount), &proof) {
+                return Err(Error::InvalidProof);
+            }
+            airdrop.claimed = safe_add(airdrop.claimed, amount)?;
+            if airdrop.claimed > airdrop.total {
+                return Err(Error::AllocationExceeded);
+            }
+
+            let mut word = self.airdrop_claimed.get((campaign, index / 256)).unwrap_or_default();
+            let bit = (index % 256) as usize;
+            word[bit / 8] |= 1 << (bit % 8);
+            self.airdrop_claimed.insert((campaign, index / 256), &word);
+            self.airdrops.insert(campaign, &airdrop);
+            self.move_balance(self.fee_pool(), msg_sender, amount)?;
+            self.env().emit_event(AirdropClaimed {
+                campaign,
+                index,
+                account: msg_sender,
+                amount
+            });
+            Ok(())
+        }
+
+        //anyone can send what's left of an expired campaign to the treasury
+        #[ink(message)]
+        pub fn sweep_airdrop(&mut self, campaign: u32) -> Result<Balance> {
+            let mut airdrop = self.airdrops.get(campaign).ok_or(Error::AirdropNotFound)?;
+            if self.env().block_timestamp() < airdrop.expiry {
+                return Err(Error::AirdropNotExpired);
+            }
+            let amount = safe_sub(airdrop.total, airdrop.claimed)?;
+            if amount > 0 {
+                airdrop.claimed = airdrop.total;
+                self.airdrops.insert(campaign, &airdrop);
+                self.move_balance(self.fee_pool(), self.treasury, amount)?;
+            }
+            self.env().emit_event(AirdropSwept {
+                campaign,
+                amount
+            });
+            Ok(amount)
+        }
+
+        #[ink(message)]
+        pub fn auction(&self) -> Option<DutchAuction> {
+            self.auction
+        }
+
+        //current per token price while an auction is selling
+        #[ink(message)]
+        pub fn auction_price(&self) -> Option<u128> {
+            let now = self.env().block_timestamp();
+            self.auction.filter(|auction| auction.is_active(now)).map(|auction| auction.price_at(now))
+        }
+
+        #[ink(message)]
+        pub fn auction_contribution(&self, account: AccountId) -> Option<AuctionContribution> {
+            self.auction_contributions.get(account)
+        }
+
+        //native value set aside for unclaimed auction refunds, the full amount paid until each buyer settles
+        #[ink(message)]
+        pub fn auction_refunds_owed(&self) -> Balance {
+            self.auction_refunds_owed
+        }
+
+        //`buy` and `buy_with_value` pay the falling price until the window ends or the supply sells out
+        #[ink(message)]
+        pub fn start_auction(&mut self, start: u64, end: u64, start_price: u128, floor_price: u128, supply: Balance, refund_to_clearing: bool) -> Result<u32> {
+            self.ensure_admin()?;
+            if end <= start || floor_price == 0 || floor_price > start_price || supply == 0 {
+                return Err(Error::InvalidAuction);
+            }
+            let now = self.env().block_timestamp();
+            let id = match self.auction {
+                Some(previous) => {
+                    //earlier buyers keep claiming at the price their auction cleared at
+                    let clearing_price = previous.clearing_price(now).ok_or(Error::AuctionOngoing)?;
+                    self.auction_clearing_prices.insert(previous.id, &clearing_price);
+                    previous.id.checked_add(1).ok_or(Error::Overflow)?
+                }
+                None => 0,
+            };
+            self.auction = Some(DutchAuction {
+                id,
+                start,
+                end,
+                start_price,
+                floor_price,
+                supply,
+                sold: 0,
+                refund_to_clearing,
+                sold_out_price: None
+            });
+            self.env().emit_event(AuctionStarted {
+                auction: id,
+                start,
+                end,
+                start_price,
+                floor_price,
+                supply
+            });
+            Ok(id)
+        }
+
+        //pays back everything above the clearing price once the buyer's auction has ended
+        #[ink(message)]
+        pub fn claim_auction_refund(&mut self) -> Result<Balance> {
+            self.settle_auction_refund(self.env().caller())
+        }
+
+        fn settle_auction_refund(&mut self, buyer: AccountId) -> Result<Balance> {
+            let contribution = match self.auction_contributions.get(buyer) {
+                Some(contribution) => contribution,
+                None => return Ok(0),
+            };
+            let clearing_price = match self.auction.filter(|auction| auction.id == contribution.auction) {
+                Some(auction) => auction.clearing_price(self.env().block_timestamp()).ok_or(Error::AuctionNotEnded)?,
+                None => self.auction_clearing_prices.get(contribution.auction).ok_or(Error::AuctionNotEnded)?,
+            };
+            let due = safe_mul(contribution.bought, clearing_price)?.div_ceil(self.token_unit());
+            let refund = contribution.paid.saturating_sub(due);
+            self.auction_contributions.remove(buyer);
+            self.auction_refunds_owed = self.auction_refunds_owed.saturating_sub(contribution.paid);
+            if refund > 0 {
+                self.env().transfer(buyer, refund).map_err(|_| Error::NativeTransferFailed)?;
+            }
+            self.env().emit_event(AuctionRefunded {
+                auction: contribution.auction,
+                buyer,
+                clearing_price,
+                refund
+            });
+            Ok(refund)
+        }
+
+        fn record_auction_purchase(&mut self, buyer: AccountId, amount: Balance, cost: Balance) -> Result<()> {
+            let now = self.env().block_timestamp();
+            let mut auction = match self.auction.filter(|auction| auction.is_active(now)) {
+                Some(auction) => auction,
+                None => return Ok(()),
+            };
+            auction.sold = safe_add(auction.sold, amount)?;
+            if auction.sold == auction.supply {
+                auction.sold_out_price = Some(auction.price_at(now));
+            }
+            self.auction = Some(auction);
+            if !auction.refund_to_clearing {
+                return Ok(());
+            }
+            //a buyer joining a new auction settles the previous one first
+            if self.auction_contributions.get(buyer).is_some_and(|contribution| contribution.auction != auction.id) {
+                self.settle_auction_refund(buyer)?;
+            }
+            let mut contribution = self.auction_contributions.get(buyer)
+                .unwrap_or(AuctionContribution { auction: auction.id, ..Default::default() });
+            contribution.paid = safe_add(contribution.paid, cost)?;
+            contribution.bought = safe_add(contribution.bought, amount)?;
+            self.auction_contributions.insert(buyer, &contribution);
+            self.auction_refunds_owed = safe_add(self.auction_refunds_owed, cost)?;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn rate_limits(&self) -> RateLimits {
+            self.rate_limits
+        }
+
+        #[ink(message)]
+        pub fn set_rate_limits(&mut self, rate_limits: RateLimits) -> Result<()> {
+            self.ensure_admin()?;
+            self.rate_limits = rate_limits;
+            Ok(())
+        }
+
+        //what the account traded in its current window, empty once the window expired
+        #[ink(message)]
+        pub fn trade_window(&self, account: AccountId) -> TradeWindow {
+            let now = self.env().block_timestamp();
+            self.trade_windows.get(account)
+                .filter(|window| now < window.start.saturating_add(self.rate_limits.window))
+                .unwrap_or(TradeWindow { start: now, bought: 0, sold: 0 })
+        }
+
+        //counts the trade against the account's window and the block volume, both reset lazily
+        fn consume_rate_limit(&mut self, account: AccountId, kind: TradeKind, amount: Balance) -> Result<()> {
+            let limits = self.rate_limits;
+            let block = self.env().block_number();
+
+            let volume = if self.block_volume.0 == block { self.block_volume.1 } else { 0 };
+            let volume = safe_add(volume, amount)?;
+            if limits.max_volume_per_block > 0 && volume > limits.max_volume_per_block {
+                return Err(Error::BlockVolumeExceeded);
+            }
+
+            let mut window = self.trade_window(account);
+            let (traded, max) = match kind {
+                TradeKind::Buy => (&mut window.bought, limits.max_bought_per_window),
+                TradeKind::Sell => (&mut window.sold, limits.max_sold_per_window),
+            };
+            *traded = safe_add(*traded, amount)?;
+            if max > 0 && *traded > max {
+                return Err(Error::RateLimited(window.start.saturating_add(limits.window)));
+            }
+
+            self.block_volume = (block, volume);
+            if limits.window > 0 {
+                self.trade_windows.insert(account, &window);
+            }
+            Ok(())
+        }
+
+        //liquid balance plus staked tokens, unbonding ones don't count
+        #[ink(message)]
+        pub fn voting_power(&self, account: AccountId) -> Balance {
+            self.balance_of(account).saturating_add(self.staked(account))
+        }
+
+        #[ink(message)]
+        pub fn staked(&self, account: AccountId) -> Balance {
+            self.stakes.get(account).unwrap_or_default().amount
+        }
+
+        #[ink(message)]
+        pub fn total_staked(&self) -> Balance {
+            self.total_staked
+        }
+
+        //amount waiting to be withdrawn and when it unlocks
+        #[ink(message)]
+        pub fn unbonding(&self, account: AccountId) -> (Balance, u64) {
+            self.unbonding.get(account).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn staking_params(&self) -> (Balance, u64) {
+            (self.emission_rate, self.unbonding_period)
+        }
+
+        //emission_rate is minted per second and shared by stake
+        #[ink(message)]
+        pub fn set_staking_params(&mut self, emission_rate: Balance, unbonding_period: u64) -> Result<()> {
+            self.ensure_admin()?;
+            //unstaked tokens must not come free before a round or spend proposal they backed closes
+            if unbonding_period < self.time_to_vote || unbonding_period < SPEND_APPROVAL_PERIOD {
+                return Err(Error::InvalidUnbondingPeriod);
+            }
+            //rewards so far accrue at the old rate
+            self.update_staking()?;
+            self.emission_rate = emission_rate;
+            self.unbonding_period = unbonding_period;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn pending_staking_rewards(&self, account: AccountId) -> Balance {
+            let stake = self.stakes.get(account).unwrap_or_default();
+            let per_share = self.staking_reward_per_share.saturating_add(self.unaccrued_per_share().unwrap_or_default());
+            stake.pending_rewards
+                .saturating_add(reward_for(stake.amount, per_share.saturating_sub(stake.reward_per_share_paid)).unwrap_or_default())
+        }
+
+        //staked tokens are held by the contract
+        #[ink(message)]
+        pub fn stake(&mut self, amount: Balance) -> Result<()> {
+            let msg_sender = self.env().caller();
+            self.ensure_compliant(Some(msg_sender), None)?;
+            if self.balance_of(msg_sender) < amount {
+                return Err(Error::InsufficientBalance);
+            }
+            self.ensure_spendable(msg_sender, amount)?;
+
+            let mut stake = self.checkpoint_stake(msg_sender)?;
+            stake.amount = safe_add(stake.amount, amount)?;
+            self.stakes.insert(msg_sender, &stake);
+            self.total_staked = safe_add(self.total_staked, amount)?;
+            self.move_balance(msg_sender, self.fee_pool(), amount)?;
+            self.env().emit_event(Staked {
+                account: msg_sender,
+                amount
+            });
+            Ok(())
+        }
+
+        //unstaked tokens stop earning and voting right away but unlock after the unbonding period
+        #[ink(message)]
+        pub fn unstake(&mut self, amount: Balance) -> Result<()> {
+            let msg_sender = self.env().caller();
+            //staked tokens count toward votes and approvals, so they stay put until those close
+            if self.locks_of(msg_sender).iter().any(|lock| lock.id == VOTE_LOCK || lock.id == SPEND_LOCK) {
+                return Err(Error::BalanceLocked);
+            }
+            let mut stake = self.checkpoint_stake(msg_sender)?;
+            if stake.amount < amount {
+                return Err(Error::InsufficientStake);
+            }
+            stake.amount -= amount;
+            self.stakes.insert(msg_sender, &stake);
+            self.total_staked = safe_sub(self.total_staked, amount)?;
+
+            //a new unstake pushes back the unlock of what is already unbonding
+            let unlock_at = self.env().block_timestamp().saturating_add(self.unbonding_period);
+            let (unbonding, _) = self.unbonding(msg_sender);
+            self.unbonding.insert(msg_sender, &(safe_add(unbonding, amount)?, unlock_at));
+            self.env().emit_event(Unstaked {
+                account: msg_sender,
+                amount,
+                unlock_at
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn withdraw_unbonded(&mut self) -> Result<Balance> {
+            let msg_sender = self.env().caller();
+            self.ensure_compliant(None, Some(msg_sender))?;
+            let (amount, unlock_at) = self.unbonding(msg_sender);
+            if amount == 0 {
+                return Ok(0);
+            }
+            if self.env().block_timestamp() < unlock_at {
+                return Err(Error::UnbondingNotElapsed);
+            }
+            self.unbonding.remove(msg_sender);
+            self.move_balance(self.fee_pool(), msg_sender, amount)?;
+            Ok(amount)
+        }
+
+        #[ink(message)]
+        pub fn claim_staking_rewards(&mut self) -> Result<Balance> {
+            let msg_sender = self.env().caller();
+            self.ensure_compliant(None, Some(msg_sender))?;
+            let mut stake = self.checkpoint_stake(msg_sender)?;
+            let value = stake.pending_rewards;
+            if value > 0 {
+                stake.pending_rewards = 0;
+                self.stakes.insert(msg_sender, &stake);
+                self.move_balance(self.fee_pool(), msg_sender, value)?;
+                self.env().emit_event(StakingRewardsClaimed {
+                    account: msg_sender,
+                    value
+                });
+            }
+            Ok(value)
+        }
+
+        //emission since the last update, capped by what can still be minted
+        fn unaccrued_emission(&self) -> Result<Balance> {
+            if self.total_staked == 0 {
+                return Ok(0);
+            }
+            let elapsed = self.env().block_timestamp().saturating_sub(self.staking_updated_at);
+            Ok(self.emission_rate.saturating_mul(elapsed as u128).min(self.remaining_mintable()))
+        }
+
+        fn unaccrued_per_share(&self) -> Result<u128> {
+            if self.total_staked == 0 {
+                return Ok(0);
+            }
+            mul_div_floor(self.unaccrued_emission()?, REWARD_PRECISION, self.total_staked)
+        }
+
+        //mints the emission into the pool, has to run before total_staked or the rate changes
+        fn update_staking(&mut self) -> Result<()> {
+            let emitted = self.unaccrued_emission()?;
+            if emitted > 0 {
+                self.staking_reward_per_share = safe_add(self.staking_reward_per_share, self.unaccrued_per_share()?)?;
+                self.mint_to(self.fee_pool(), emitted)?;
+            }
+            self.staking_updated_at = self.env().block_timestamp();
+            Ok(())
+        }
+
+        fn checkpoint_stake(&mut self, account: AccountId) -> Result<Stake> {
+            self.update_staking()?;
+            let mut stake = self.stakes.get(account).unwrap_or_default();
+            let earned = reward_for(stake.amount, safe_sub(self.staking_reward_per_share, stake.reward_per_share_paid)?)?;
+            stake.pending_rewards = safe_add(stake.pending_rewards, earned)?;
+            stake.reward_per_share_paid = self.staking_reward_per_share;
+            Ok(stake)
+        }
+
+        //redeems tokens for native currency at the current price, paid from the contract balance
+        #[ink(message)]
+        pub fn sell(&mut self, amount: u128, min_native_out: Balance) -> Result<()>{
+            let msg_sender = self.env().caller();
+            self.ensure_compliant(Some(msg_sender), None)?;
+            //counting fee
+            let fee_to_take = self.trade_fee(FeeKind::Sell, msg_sender, amount);
+            if self.balance_of(msg_sender) < safe_add(amount, fee_to_take)? {
+                return Err(Error::InsufficientBalance);
+            }
+            self.ensure_spendable(msg_sender, safe_add(amount, fee_to_take)?)?;
+            let price = self.spot_price()?;
+            let payout = self.quote_sell(amount)?;
+            if payout < min_native_out {
+                return Err(Error::SlippageExceeded);
+            }
+            //unclaimed auction refunds aren't available for redemptions
+            if self.env().balance().saturating_sub(self.auction_refunds_owed) < payout {
+                return Err(Error::InsufficientReserve);
+            }
+            self.consume_rate_limit(msg_sender, TradeKind::Sell, amount)?;
+
+            self.burn_from(msg_sender, amount)?;
+            if fee_to_take > 0 {
+                self.move_balance(msg_sender, self.fee_pool(), fee_to_take)?;
+            }
+            self.accrue_fee(fee_to_take)?;
+            self.env().transfer(msg_sender, payout).map_err(|_| Error::NativeTransferFailed)?;
+            self.env().emit_event(Sold {
+                seller: msg_sender,
+                amount,
+                price,
+                fee: fee_to_take,
+                payout
+            });
+            Ok(())
+        }
+
+        //holders burn their own tokens, the admin can burn from anyone
+        #[ink(message)]
+        pub fn burn(&mut self, value: u128, address: AccountId) -> Result<()> {
+            if self.env().caller() != address {
+                self.ensure_admin()?;
+            }
+            self.burn_from(address, value)
+        }
+
+        //every supply change goes through mint_to/burn_from so total_supply == sum of balances
+        fn mint_to(&mut self, to: AccountId, value: Balance) -> Result<()> {
+            self.update_rewards(to)?;
+            let balance = safe_add(self.balance_of(to), value)?;
+            let total_supply = safe_add(self.total_supply, value)?;
+            if total_supply > self.max_supply {
+                return Err(Error::CapExceeded);
+            }
+            self.total_supply = total_supply;
+            self.balances.insert(to, &balance);
+            self.env().emit_event(Transfer {
+                from: None,
+                to,
+                value
+            });
+            Ok(())
+        }
+
+        fn burn_from(&mut self, from: AccountId, value: Balance) -> Result<()> {
+            self.update_rewards(from)?;
+            let balance = safe_sub(self.balance_of(from), value)?;
+            self.total_supply = safe_sub(self.total_supply, value)?;
+            self.balances.insert(from, &balance);
+            Ok(())
+        }
+
+        fn move_balance(&mut self, from: AccountId, to: AccountId, value: Balance) -> Result<()> {
+            self.update_rewards(from)?;
+            self.update_rewards(to)?;
+            self.balances.insert(from, &safe_sub(self.balance_of(from), value)?);
+            let to_balance = safe_add(self.balance_of(to), value)?;
+            self.balances.insert(to, &to_balance);
+            self.env().emit_event(Transfer {
+                from: Some(from),
+                to,
+                value
+            });
+            Ok(())
+        }
+
+        fn ensure_admin(&self) -> Result<()> {
+            if self.env().caller() != self.admin {
+                return Err(Error::NotAdmin);
+            }
+            Ok(())
+        }
+
+        //asks a contract recipient to accept the tokens, plain accounts are not called
+        fn do_safe_transfer_check(&mut self, operator: AccountId, from: AccountId, to: AccountId, value: Balance) -> Result<()> {
+            if !self.env().is_contract(&to) {
+                return Ok(());
+            }
+            let result = build_call::<Environment>()
+                .call(to)
+                .gas_limit(0)
+                .call_flags(CallFlags::default().set_allow_reentry(true))
+                .exec_input(
+                    ExecutionInput::new(Selector::new(ink::selector_bytes!("PSP22Receiver::before_received")))
+                        .push_arg(operator)
+                        .push_arg(from)
+                        .push_arg(value)
+                        .push_arg(Vec::<u8>::new()),
+                )
+                .returns::<core::result::Result<(), PSP22ReceiverError>>()
+                .try_invoke();
+            match result {
+                Ok(Ok(Ok(()))) => Ok(()),
+                //rejected, trapped or does not implement the receiver
+                _ => Err(Error::TransferRejectedByReceiver),
+            }
+        }
+
+        //// Modifies the code which is used to execute calls to this contract address (`AccountId`).
+        ////
+        //// We use this to upgrade the contract logic. We don't do any authorization here, any caller
+        //// can execute this method. In a production contract you would do some authorization here.
+        #[ink(message)]
+        pub fn set_code(&mut self, code_hash: [u8; 32]) {
+            ink::env::set_code_hash(&code_hash).unwrap_or_else(|err| {
+                panic!(
+                    "Failed to `set_code_hash` to {:?} due to {:?}",
+                    code_hash, err
+                )
+            });
+            ink::env::debug_println!("Switched code hash to {:?}.", code_hash);
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {    
+        use super::*;
+
+        fn default_accounts() -> ink::env::test::DefaultAccounts<Environment> {
+            ink::env::test::default_accounts::<Environment>()
+        }
+
+        fn alice() -> AccountId {
+            default_accounts().alice
+        }
+
+        fn bob() -> AccountId {
+            default_accounts().bob
+        }
+
+        fn contract_id() -> AccountId {
+            AccountId::from([0x42; 32])
+        }
+
+        //deploys at its own address so the fee pool doesn't share alice's account
+        fn deploy(total_supply: Balance) -> Erc20 {
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(contract_id());
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(contract_id(), 1000000);
+            Erc20::new(total_supply)
+        }
+
+        fn deploy_with_curve(total_supply: Balance, pricing_curve: PricingCurve) -> Erc20 {
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(contract_id());
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(contract_id(), 1000000);
+            Erc20::new_with_curve(total_supply, pricing_curve)
+        }
+
+        fn assert_supply_invariant(contract: &Erc20) {
+            let accounts = default_accounts();
+            let holders = [accounts.alice, accounts.bob, accounts.charlie, accounts.django, accounts.eve, accounts.frank, contract_id()];
+            let sum: Balance = holders.iter().map(|holder| contract.balance_of(*holder) + contract.reserved_balance(*holder)).sum();
+            assert_eq!(contract.total_supply(), sum);
+        }
+
+        //the account of an ecdsa key, blake2 of the compressed public key
+        fn ecdsa_account(secret: &secp256k1::SecretKey) -> AccountId {
+            let public_key = secp256k1::PublicKey::from_secret_key(secp256k1::SECP256K1, secret).serialize();
+            let mut account = [0u8; 32];
+            ink::env::hash_bytes::<Blake2x256>(&public_key, &mut account);
+            AccountId::from(account)
+        }
+
+        //signs a permit the way an off-chain wallet would, v goes last
+        fn sign_permit(secret: &secp256k1::SecretKey, domain_separator: [u8; 32], spender: AccountId, tokens: Balance, nonce: u64, deadline: u64) -> [u8; 65] {
+            let mut message_hash = [0u8; 32];
+            ink::env::hash_encoded::<Blake2x256, _>(
+                &(domain_separator, ecdsa_account(secret), spender, tokens, nonce, deadline),
+                &mut message_hash,
+            );
+            let message = secp256k1::Message::from_slice(&message_hash).unwrap();
+            let (recovery_id, compact) = secp256k1::SECP256K1.sign_ecdsa_recoverable(&message, secret).serialize_compact();
+            let mut signature = [0u8; 65];
+            signature[..64].copy_from_slice(&compact);
+            signature[64] = recovery_id.to_i32() as u8;
+            signature
+        }
+
+        #[ink::test]
         fn returns_zero_balance(){
             let contract = Erc20::new(100);
             assert_eq!(contract.balance_of(bob()), 0);
         }
 
         #[ink::test]
-        fn returns_total_supply() {
-            let contract = Erc20::new(777);
-            assert_eq!(contract.total_supply(), 777);
+        fn returns_total_supply() {
+            let contract = Erc20::new(777);
+            assert_eq!(contract.total_supply(), 777);
+        }
+
+        #[ink::test]
+        fn returns_balance_of_msg_sender() {
+            let contract = Erc20::new(100);
+            assert_eq!(contract.balance_of(alice()), 100);
+        }
+
+        #[ink::test]
+        fn transfer_happens(){
+            let mut contract = Erc20::new(100);
+            let accounts = 
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            
+            // default balance of bob
+            assert_eq!(contract.balance_of(accounts.bob), 0);
+            // transfer method
+            assert_eq!(contract.transfer(accounts.bob, 20), Ok(()));
+            //eventual balance
+            assert_eq!(contract.balance_of(accounts.bob), 20);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "off-chain environment does not support contract invocation")]
+        fn transfer_to_contract_calls_receiver(){
+            let mut contract = Erc20::new(100);
+            let accounts = default_accounts();
+            //plain account, no callback
+            assert_eq!(contract.transfer(accounts.bob, 10), Ok(()));
+            //contract account, the off-chain env can't execute the callback
+            ink::env::test::set_contract::<ink::env::DefaultEnvironment>(accounts.charlie);
+            let _ = contract.transfer(accounts.charlie, 10);
+        }
+
+        #[ink::test]
+        fn transfer_error_when_insfficient_funds(){
+            let mut contract = Erc20::new(0);
+            let accounts = 
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            assert_eq!(contract.balance_of(accounts.bob), 0);
+            assert!(contract.transfer(accounts.bob, 100).is_err());
+            assert_eq!(contract.transfer(accounts.bob, 100), Err(Error::InsufficientBalance));
+        }
+
+        #[ink::test]
+        fn transfer_from_happens(){
+            let mut contract = Erc20::new(100);
+            let accounts = 
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // default balance of bob
+            assert_eq!(contract.balance_of(accounts.bob), 0);
+            assert_eq!(contract.approve(accounts.bob, 20), Ok(()));
+            //nobody else can spend bob's allowance, not even toward bob
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.transfer_from(accounts.alice, accounts.bob, 20), Err(Error::InsufficientAllowance));
+            // transfer method, bob sends alice's tokens on to django
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.transfer_from(accounts.alice, accounts.django, 20), Ok(()));
+            //eventual balance
+            assert_eq!(contract.balance_of(accounts.django), 20);
+            assert_eq!(contract.balance_of(accounts.bob), 0);
+            //allowance is spent
+            assert_eq!(contract.allowances(accounts.alice, accounts.bob), 0);
+            assert_eq!(contract.transfer_from(accounts.alice, accounts.bob, 1), Err(Error::InsufficientAllowance));
+        }
+
+        #[ink::test]
+        fn transfer_from_unlimited_allowance_is_not_spent(){
+            let mut contract = Erc20::new(100);
+            let accounts = default_accounts();
+            assert_eq!(contract.approve(accounts.bob, UNLIMITED_ALLOWANCE), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.transfer_from(accounts.alice, accounts.charlie, 60), Ok(()));
+            assert_eq!(contract.allowances(accounts.alice, accounts.bob), UNLIMITED_ALLOWANCE);
+            //balance is enforced at spend time
+            assert_eq!(contract.transfer_from(accounts.alice, accounts.charlie, 60), Err(Error::InsufficientBalance));
+        }
+
+        #[ink::test]
+        fn transfer_from_error_when_insfficient_funds(){
+            let mut contract = Erc20::new(0);
+            let accounts = 
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            assert_eq!(contract.balance_of(accounts.bob), 0);
+            assert!(contract.transfer_from(accounts.alice, accounts.bob, 100).is_err());
+            assert_eq!(contract.transfer_from(accounts.alice, accounts.bob, 100), Err(Error::InsufficientBalance));
+        }
+
+        #[ink::test]
+        fn transfer_from_error_insufficient_allowance(){
+            let mut contract = Erc20::new(100);
+            let accounts = 
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            assert_eq!(contract.approve(accounts.bob, 10), Ok(()));
+            assert_eq!(contract.allowances(accounts.alice, accounts.bob), 10);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.transfer_from(accounts.alice, accounts.bob, 100), Err(Error::InsufficientAllowance));
+        }
+
+
+        #[ink::test]
+        fn approve_happens(){
+            let mut contract = Erc20::new(100);
+            let accounts = 
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            assert_eq!(contract.approve(accounts.bob, 10), Ok(()));
+            assert_eq!(contract.allowances(accounts.alice, accounts.bob), 10);
+        }
+
+        #[ink::test]
+        fn approve_above_balance_happens(){
+            let mut contract = Erc20::new(100);
+            let accounts = 
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            assert_eq!(contract.approve(accounts.bob, 110), Ok(()));
+            assert_eq!(contract.allowances(accounts.alice, accounts.bob), 110);
+        }
+
+        #[ink::test]
+        fn increase_and_decrease_allowance(){
+            let mut contract = Erc20::new(100);
+            let accounts = default_accounts();
+            assert_eq!(contract.increase_allowance(accounts.bob, 10), Ok(()));
+            assert_eq!(contract.increase_allowance(accounts.bob, 5), Ok(()));
+            assert_eq!(contract.allowances(accounts.alice, accounts.bob), 15);
+            assert_eq!(contract.decrease_allowance(accounts.bob, 16), Err(Error::InsufficientAllowance));
+            assert_eq!(contract.decrease_allowance(accounts.bob, 15), Ok(()));
+            assert_eq!(contract.allowances(accounts.alice, accounts.bob), 0);
+            //increasing past the sentinel stays unlimited
+            assert_eq!(contract.approve(accounts.bob, UNLIMITED_ALLOWANCE), Ok(()));
+            assert_eq!(contract.increase_allowance(accounts.bob, 1), Ok(()));
+            assert_eq!(contract.allowances(accounts.alice, accounts.bob), UNLIMITED_ALLOWANCE);
+        }
+
+        #[ink::test]
+        fn permit_rejects_expired_and_invalid_signatures(){
+            let mut contract = Erc20::new(100);
+            let accounts = default_accounts();
+            assert_eq!(contract.nonces(accounts.alice), 0);
+
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(10);
+            assert_eq!(contract.permit(accounts.alice, accounts.bob, 10, 9, [0u8; 65]), Err(Error::PermitExpired));
+            assert_eq!(contract.permit(accounts.alice, accounts.bob, 10, 10, [0u8; 65]), Err(Error::InvalidSignature));
+            assert_eq!(contract.allowances(accounts.alice, accounts.bob), 0);
+            assert_eq!(contract.nonces(accounts.alice), 0);
+        }
+
+        #[ink::test]
+        fn permit_accepts_a_signature_once(){
+            let mut contract = deploy(100);
+            let accounts = default_accounts();
+            let secret = secp256k1::SecretKey::from_slice(&[7u8; 32]).unwrap();
+            let owner = ecdsa_account(&secret);
+            assert_eq!(contract.transfer(owner, 20), Ok(()));
+
+            //signed for another deployment
+            let foreign = sign_permit(&secret, [1u8; 32], accounts.bob, 10, 0, 100);
+            assert_eq!(contract.permit(owner, accounts.bob, 10, 100, foreign), Err(Error::InvalidSignature));
+            let signature = sign_permit(&secret, contract.domain_separator(), accounts.bob, 10, 0, 100);
+            //the signature covers every argument
+            assert_eq!(contract.permit(owner, accounts.bob, 11, 100, signature), Err(Error::InvalidSignature));
+            assert_eq!(contract.permit(accounts.alice, accounts.bob, 10, 100, signature), Err(Error::InvalidSignature));
+            assert_eq!(contract.nonces(owner), 0);
+
+            //anyone can submit it
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.permit(owner, accounts.bob, 10, 100, signature), Ok(()));
+            assert_eq!(contract.allowances(owner, accounts.bob), 10);
+            assert_eq!(contract.nonces(owner), 1);
+            //the nonce moved on, so it can't be replayed
+            assert_eq!(contract.permit(owner, accounts.bob, 10, 100, signature), Err(Error::InvalidSignature));
+            assert_eq!(contract.nonces(owner), 1);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.transfer_from(owner, accounts.django, 10), Ok(()));
+            assert_eq!(contract.balance_of(accounts.django), 10);
+        }
+
+        #[ink::test]
+        fn initiate_voting_works(){
+            let mut contract = Erc20::new(100);
+            let accounts = 
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let new_price: u128 = 50;
+            //is_voting false
+            assert_eq!(contract.is_voting_happening, false);
+            //transfer 1 token to bob
+            assert_eq!(contract.transfer(accounts.bob, 1), Ok(()));
+            assert_eq!(contract.balance_of(accounts.bob), 1);
+            //call the function, should return error
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.initiate_voting(new_price), Err(Error::InsufficientBalance));
+            //transfer 9 more tokens to bob
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.transfer(accounts.bob, 9), Ok(()));
+            assert_eq!(contract.balance_of(accounts.bob), 10);
+            //voting end time has not began to run yet
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.voting_end_time, 0);
+            //set block timestamp to 1
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1);
+            // successful initiate voting
+            assert_eq!(contract.initiate_voting(new_price), Ok(()));
+
+            // ink::env::debug_println!("block time stamp: {}", ink::env::block_timestamp::<ink::env::DefaultEnvironment>()); 
+            
+            //is_voting true
+            assert_eq!(contract.is_voting_happening, true);
+            //time to vote began
+            assert_eq!(contract.voting_end_time, contract.get_current_timestemp() + contract.time_to_vote);
+            assert_eq!(contract.voting_begin_time, contract.get_current_timestemp());
+        }
+
+        #[test]
+        fn permill_rounding() {
+            assert_eq!(Permill::from_percent(10).mul_floor(100), 10);
+            assert_eq!(Permill::from_percent(10).mul_floor(109), 10);
+            assert_eq!(Permill::from_percent(10).mul_ceil(101), Ok(11));
+            assert_eq!(Permill::from_percent(200), Permill::from_parts(Permill::ACCURACY));
+            assert_eq!(Permill::from_parts(Permill::ACCURACY).mul_floor(u128::MAX), u128::MAX);
+            assert_eq!(Permill::from_parts(Permill::ACCURACY).mul_ceil(u128::MAX), Ok(u128::MAX));
+            assert_eq!(Permill::from_percent(5).mul_ceil(u128::MAX), Ok(u128::MAX / 20 + 1));
+            //encoded values above 100% decode clamped
+            let encoded = scale::Encode::encode(&u32::MAX);
+            assert_eq!(<Permill as scale::Decode>::decode(&mut &encoded[..]), Ok(Permill::from_parts(Permill::ACCURACY)));
+        }
+
+        #[ink::test]
+        fn proposal_threshold_holds_for_large_supplies(){
+            //f32 can't tell these balances apart
+            let total_supply: u128 = (1 << 24) * 10 + 10;
+            let mut contract = Erc20::new(total_supply);
+            let accounts = default_accounts();
+            let proposal_minimum = Permill::from_percent(10).mul_ceil(total_supply).unwrap();
+            assert_eq!(proposal_minimum, (1 << 24) + 1);
+            assert_eq!(contract.transfer(accounts.bob, proposal_minimum - 1), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.initiate_voting(50), Err(Error::InsufficientBalance));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.transfer(accounts.bob, 1), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.initiate_voting(50), Ok(()));
+        }
+
+        #[ink::test]
+        fn voting_threshold_holds_for_max_supply(){
+            let mut contract = Erc20::new(u128::MAX);
+            let accounts = default_accounts();
+            let voting_minimum = Permill::from_percent(5).mul_ceil(u128::MAX).unwrap();
+            assert_eq!(contract.transfer(accounts.bob, u128::MAX / 5), Ok(()));
+            assert_eq!(contract.transfer(accounts.charlie, voting_minimum - 1), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.initiate_voting(50), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.vote(60), Err(Error::InsufficientBalance));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.transfer(accounts.charlie, 1), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.vote(60), Ok(()));
+        }
+
+        #[ink::test]
+        fn set_voting_thresholds_works(){
+            let mut contract = Erc20::new(100);
+            let accounts = default_accounts();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.set_voting_thresholds(Permill::from_percent(1), Permill::from_percent(1)), Err(Error::NotAdmin));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.set_voting_thresholds(Permill::from_percent(20), Permill::from_percent(2)), Ok(()));
+            assert_eq!(contract.voting_thresholds(), (Permill::from_percent(20), Permill::from_percent(2)));
+            assert_eq!(contract.transfer(accounts.bob, 19), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.initiate_voting(50), Err(Error::InsufficientBalance));
+        }
+
+        #[ink::test]
+        fn vote_accepted(){
+            let mut contract = Erc20::new(100);
+            let accounts = default_accounts();
+            let mut new_price: u128 = 50;
+
+            //call the vote function when voting is not happening
+            assert_eq!(contract.transfer(accounts.charlie, 10), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.vote(new_price), Err(Error::VotingIsNotOngoing));
+            assert_eq!(contract.initiate_voting(new_price), Ok(()));
+            assert_eq!(contract.current_winner, new_price);
+            assert_eq!(contract.votes_for(50), 10);
+            
+            //set bob as contract caller
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            //call iniciate voting from bob's account
+            assert_eq!(contract.initiate_voting(new_price), Err(Error::VotingIsAlreadyOngoing));
+            assert_eq!(contract.balance_of(accounts.bob), 0);
+            //call the vote function
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.vote(new_price), Err(Error::InsufficientBalance));
+            
+            //transfer 15 tokens to bob
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.transfer(accounts.bob, 15), Ok(()));
+            assert_eq!(contract.balance_of(accounts.bob), 15);
+
+            //change caller to bob
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            //he has not voted yet
+            assert_eq!(contract.already_voted.get((contract.voting_number, accounts.bob)).unwrap_or_default(), false);
+            //call the vote function
+            new_price = 60;
+            assert_eq!(contract.vote(new_price), Ok(()));
+            //check the mapping: price option voted for == account voting power
+            assert_eq!(contract.votes_for(60), contract.balance_of(bob()));
+            assert_eq!(contract.current_winner, 60);
+            assert_eq!(contract.votes_for(60), 15);
+            //bob has already voted
+            assert_eq!(contract.already_voted.get((contract.voting_number, accounts.bob)).unwrap_or_default(), true);
+            assert_eq!(contract.vote(new_price), Err(Error::AccountAlreadyVoted));
+
+            //transfer 5 to charlie
+            assert_eq!(contract.balance_of(accounts.charlie), 10);
+            //with transfer
+            assert_eq!(contract.transfer(accounts.charlie, 5), Err(Error::BalanceLocked));
+            assert_eq!(contract.approve(accounts.charlie, 5), Ok(()));
+            assert_eq!(contract.allowances(accounts.bob, accounts.charlie), 5);
+            //with transfer_from
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.transfer_from(accounts.bob, accounts.charlie, 5), Err(Error::BalanceLocked));
+            assert_eq!(contract.balance_of(accounts.bob), 15);
+            assert_eq!(contract.balance_of(accounts.charlie), 10);
+            assert_eq!(contract.current_winner, 60);
+
+        }
+
+        #[ink::test]
+        pub fn end_voting_success(){
+            let mut contract = Erc20::new(100);
+            let accounts: ink::env::test::DefaultAccounts<ink::env::DefaultEnvironment> = default_accounts();
+            let new_price: u128 = 50;
+
+            //call the vote function when voting is not happening
+            assert_eq!(contract.transfer(accounts.bob, 10), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.vote(new_price), Err(Error::VotingIsNotOngoing));
+            assert_eq!(contract.end_voting(), Err(Error::VotingIsNotOngoing));
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1);
+            assert_eq!(contract.initiate_voting(new_price), Ok(()));
+            
+            ink::env::debug_println!("voting_end_time: {}", contract.voting_end_time);
+
+            //checks the time has elapsed
+            assert_eq!(contract.end_voting(), Err(Error::TimeToVoteNotElapsed));
+            //logic for time travel
+            ink::env::debug_println!("block number: {}", ink::env::block_number::<ink::env::DefaultEnvironment>());
+            ink::env::debug_println!("block time stamp: {}", ink::env::block_timestamp::<ink::env::DefaultEnvironment>()); 
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(contract.voting_end_time);
+            ink::env::debug_println!("block number: {}", ink::env::block_number::<ink::env::DefaultEnvironment>());
+            ink::env::debug_println!("block time stamp: {}", ink::env::block_timestamp::<ink::env::DefaultEnvironment>());
+            assert_eq!(contract.end_voting(), Ok(()));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::debug_println!("block number: {}", ink::env::block_number::<ink::env::DefaultEnvironment>());
+            ink::env::debug_println!("block time stamp: {}", ink::env::block_timestamp::<ink::env::DefaultEnvironment>());
+
+            assert_eq!(contract.voting_end_time, 0);
+            assert_eq!(contract.voting_begin_time, 0);
+            ink::env::debug_println!("contract.already_voted.get(accounts.bob): {}", contract.already_voted.get((contract.voting_number, accounts.bob)).unwrap());
+
+            // assert_eq!(contract.already_voted.get(accounts.bob).unwrap_or(false), false);
+            assert_eq!(contract.is_voting_happening, false);
+            assert_eq!(contract.current_winner, 50);
+            assert_eq!(contract.votes_for(50), 10);
+        }
+
+        #[ink::test]
+        pub fn buy_works (){
+            let mut contract = Erc20::new(100);
+            let accounts: ink::env::test::DefaultAccounts<ink::env::DefaultEnvironment> = default_accounts();
+
+    
+            //total supply and contract balance are in their initial values
+            assert_eq!(ink::env::balance::<ink::env::DefaultEnvironment>(), 1000000);
+            assert_eq!(contract.total_supply, 100);
+
+            // assert_eq!(contract.transfer(accounts.bob, 50), Ok(()));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            //transfer of 50 ETH to the contract
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(50);
+            //incorrect amount passed to the buy method
+            assert_eq!(contract.buy(8), Err(Error::ExactAmountOfWeiRequired));
+            //correct amount passed to the buy method
+            assert_eq!(contract.buy(10), Ok(()));
+            //total supply +10
+            assert_eq!(contract.total_supply, 110);
+            //bob + 10
+            assert_eq!(contract.balance_of(accounts.bob), 10);
+            //check that contract has reveiced 50 ETH
+            assert_eq!(ink::env::balance::<ink::env::DefaultEnvironment>(), 1000050);            
         }
 
         #[ink::test]
-        fn returns_balance_of_msg_sender() {
-            let contract = Erc20::new(100);
-            assert_eq!(contract.balance_of(alice()), 100);
+        pub fn buy_and_sell_fees_go_to_the_pool (){
+            let mut contract = deploy(1000);
+            let accounts = default_accounts();
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000);
+            //1% of 200
+            assert_eq!(contract.buy(200), Ok(()));
+            assert_eq!(contract.balance_of(accounts.bob), 198);
+            assert_eq!(contract.balance_of(contract.fee_pool()), 2);
+            assert_eq!(contract.total_supply(), 1200);
+            assert_supply_invariant(&contract);
+
+            //1% of 100 on top of the amount
+            assert_eq!(contract.sell(100, 0), Ok(()));
+            assert_eq!(contract.balance_of(accounts.bob), 97);
+            assert_eq!(contract.balance_of(contract.fee_pool()), 3);
+            assert_eq!(contract.total_supply(), 1100);
+            assert_supply_invariant(&contract);
+
+            //fees are burned out of the pool, not from the caller
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(604800);
+            assert_eq!(contract.finalize_burn_epoch(), Ok(0));
+            assert_eq!(contract.balance_of(contract.fee_pool()), 0);
+            assert_eq!(contract.balance_of(accounts.bob), 97);
+            assert_eq!(contract.total_supply(), 1097);
+            assert_supply_invariant(&contract);
         }
 
         #[ink::test]
-        fn transfer_happens(){
-            let mut contract = Erc20::new(100);
-            let accounts = 
-                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
-            
-            // default balance of bob
-            assert_eq!(contract.balance_of(accounts.bob), 0);
-            // transfer method
-            assert_eq!(contract.transfer(accounts.bob, 20), Ok(()));
-            //eventual balance
-            assert_eq!(contract.balance_of(accounts.bob), 20);
+        pub fn fee_schedule_tiers_and_exemptions (){
+            let mut contract = deploy(100_000);
+            let accounts = default_accounts();
+            let schedule = FeeSchedule {
+                buy: Permill::from_percent(2),
+                sell: Permill::from_percent(4),
+                transfer: Permill::from_percent(1),
+                tiers: vec![
+                    FeeTier { min_amount: 1000, discount: Permill::from_percent(50) },
+                    FeeTier { min_amount: 10_000, discount: Permill::from_percent(100) },
+                ],
+                split: contract.fees().split
+            };
+
+            //only the admin, only within the cap, only sorted tiers
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.set_fees(schedule.clone()), Err(Error::NotAdmin));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.set_fees(FeeSchedule { sell: Permill::from_percent(11), ..schedule.clone() }), Err(Error::FeeTooHigh));
+            assert_eq!(contract.set_fees(FeeSchedule { tiers: schedule.tiers.iter().rev().copied().collect(), ..schedule.clone() }), Err(Error::InvalidFeeTiers));
+            assert_eq!(contract.set_fees(schedule.clone()), Ok(()));
+            assert_eq!(contract.fees(), schedule);
+
+            //1% below the first tier
+            assert_eq!(contract.transfer(accounts.bob, 500), Ok(()));
+            assert_eq!(contract.balance_of(accounts.bob), 495);
+            //half of 1% from 1000 on
+            assert_eq!(contract.transfer(accounts.bob, 2000), Ok(()));
+            assert_eq!(contract.balance_of(accounts.bob), 495 + 1990);
+            //free from 10000 on
+            assert_eq!(contract.transfer(accounts.charlie, 10_000), Ok(()));
+            assert_eq!(contract.balance_of(accounts.charlie), 10_000);
+            assert_eq!(contract.balance_of(contract.fee_pool()), 15);
+            assert_supply_invariant(&contract);
+
+            //exempt accounts pay nothing on either side
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.set_fee_exempt(accounts.django, true), Err(Error::NotAdmin));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.set_fee_exempt(accounts.django, true), Ok(()));
+            assert_eq!(contract.transfer(accounts.django, 500), Ok(()));
+            assert_eq!(contract.balance_of(accounts.django), 500);
+
+            //4% sell fee on top of the amount
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.sell(100, 0), Ok(()));
+            assert_eq!(contract.balance_of(accounts.bob), 2485 - 104);
+            assert_eq!(contract.balance_of(contract.fee_pool()), 19);
+            assert_supply_invariant(&contract);
         }
 
         #[ink::test]
-        fn transfer_error_when_insfficient_funds(){
-            let mut contract = Erc20::new(0);
-            let accounts = 
-                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        pub fn fees_are_split_between_burn_treasury_and_holders (){
+            let mut contract = deploy(1000);
+            let accounts = default_accounts();
+            assert_eq!(contract.transfer(accounts.bob, 500), Ok(()));
+            assert_eq!(contract.set_treasury(accounts.eve), Ok(()));
+            let split = FeeSplit {
+                burn: Permill::from_percent(50),
+                treasury: Permill::from_percent(20),
+                holders: Permill::from_percent(30)
+            };
+            let fees = FeeSchedule { transfer: Permill::from_percent(10), ..contract.fees() };
+            assert_eq!(contract.set_fees(FeeSchedule { split: FeeSplit { holders: Permill::from_percent(31), ..split }, ..fees.clone() }), Err(Error::InvalidFeeSplit));
+            assert_eq!(contract.set_fees(FeeSchedule { split, ..fees }), Ok(()));
 
-            assert_eq!(contract.balance_of(accounts.bob), 0);
-            assert!(contract.transfer(accounts.bob, 100).is_err());
-            assert_eq!(contract.transfer(accounts.bob, 100), Err(Error::InsufficientBalance));
+            //fee of 10: 5 burned, 2 to the treasury, 3 to holders of which 2 survive the per token rounding
+            assert_eq!(contract.transfer(accounts.charlie, 100), Ok(()));
+            assert_eq!(contract.balance_of(accounts.eve), 2);
+            assert_eq!(contract.balance_of(contract.fee_pool()), 8);
+            assert_eq!(contract.reward_pool, 2);
+            assert_eq!(contract.fee_to_burn(), 6);
+            //3 over the 992 tokens held outside the pool
+            assert_eq!(contract.claimable_rewards(accounts.alice), 400 * 3 / 992);
+            assert_eq!(contract.claimable_rewards(accounts.bob), 500 * 3 / 992);
+            assert_eq!(contract.claimable_rewards(accounts.charlie), 0);
+
+            //a later receiver doesn't earn past rewards
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.transfer(accounts.django, 10), Ok(()));
+            assert_eq!(contract.claimable_rewards(accounts.django), 0);
+            assert_eq!(contract.claim_rewards(), Ok(1));
+            assert_eq!(contract.claimable_rewards(accounts.bob), 0);
+            assert_eq!(contract.balance_of(accounts.bob), 491);
+            assert_supply_invariant(&contract);
         }
 
         #[ink::test]
-        fn transfer_from_happens(){
-            let mut contract = Erc20::new(100);
-            let accounts = 
-                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
-            // default balance of bob
+        pub fn holder_reward_dust_is_burned (){
+            let mut contract = deploy(10_000_000_000_000_000);
+            let accounts = default_accounts();
+            let split = FeeSplit { burn: Permill::from_percent(50), treasury: Permill::from_parts(0), holders: Permill::from_percent(50) };
+            let fees = FeeSchedule { transfer: Permill::from_percent(10), split, ..contract.fees() };
+            assert_eq!(contract.set_fees(fees), Ok(()));
+
+            //5 to holders over a supply of 1e16 still pays out
+            assert_eq!(contract.transfer(accounts.bob, 100), Ok(()));
+            assert_eq!(contract.reward_per_token, 500);
+            assert_eq!(contract.reward_pool, 4);
+            assert_eq!(contract.fee_to_burn(), 6);
+            assert_eq!(contract.claimable_rewards(accounts.alice), 4);
+            assert_supply_invariant(&contract);
+        }
+
+        #[ink::test]
+        pub fn buyback_burns_from_the_pool (){
+            let mut contract = deploy(1000);
+            let accounts = default_accounts();
+            assert_eq!(contract.transfer(accounts.charlie, 500), Ok(()));
+            assert_eq!(contract.set_treasury(accounts.eve), Ok(()));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(contract.buyback(500), Err(Error::BuybackPoolNotSet));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.set_buyback_config(Some(accounts.charlie), 600), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.approve(contract_id(), 120), Ok(()));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.buyback(500), Err(Error::NotTreasury));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            let charlie_native = ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.charlie).unwrap_or_default();
+            //500 at price 5
+            assert_eq!(contract.buyback(500), Ok(100));
+            assert_eq!(contract.balance_of(accounts.charlie), 400);
+            assert_eq!(contract.total_supply(), 900);
+            assert_eq!(ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.charlie), Ok(charlie_native + 500));
+            assert_supply_invariant(&contract);
+
+            //100 left of the 600 cap this epoch
+            assert_eq!(contract.buyback(105), Err(Error::BuybackCapExceeded));
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(604800);
+            //the cap resets, the pool's approval does not
+            assert_eq!(contract.buyback(105), Err(Error::InsufficientAllowance));
+            //only what buys a whole token is paid
+            assert_eq!(contract.buyback(4), Err(Error::BuybackTooSmall));
+            let charlie_native = ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.charlie).unwrap_or_default();
+            assert_eq!(contract.buyback(104), Ok(20));
+            assert_eq!(ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.charlie), Ok(charlie_native + 100));
+            assert_eq!(contract.allowances(accounts.charlie, contract_id()), 0);
+        }
+
+        #[ink::test]
+        pub fn treasury_spends_only_the_surplus (){
+            let mut contract = deploy(1000);
+            let accounts = default_accounts();
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(contract_id(), 8000);
+            //1000 tokens at price 5
+            assert_eq!(contract.treasury_balance(), 8000);
+            assert_eq!(contract.required_reserve(), Ok(5000));
+            assert_eq!(contract.treasury_surplus(), Ok(3000));
+            assert_eq!(contract.reserve_ratio(), Ok(1_600_000));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.treasury_spend(accounts.bob, 100), Err(Error::InsufficientBalance));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.treasury_spend(accounts.eve, 3001), Err(Error::ReserveRatioTooLow));
+            assert_eq!(contract.treasury_spend(accounts.eve, 3000), Ok(0));
+            let eve_native = ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.eve).unwrap_or_default();
+            assert_eq!(contract.approve_treasury_spend(0), Ok(()));
+            assert_eq!(contract.execute_treasury_spend(0), Ok(()));
+            assert_eq!(ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.eve), Ok(eve_native + 3000));
+            assert_eq!(contract.reserve_ratio(), Ok(1_000_000));
+
+            //a lower minimum frees part of the reserve
+            assert_eq!(contract.set_min_reserve_ratio(Permill::from_percent(50)), Ok(()));
+            assert_eq!(contract.treasury_surplus(), Ok(2500));
+            //buybacks come out of the surplus too
+            assert_eq!(contract.set_buyback_config(Some(accounts.alice), 10_000), Ok(()));
+            assert_eq!(contract.approve(contract_id(), UNLIMITED_ALLOWANCE), Ok(()));
+            assert_eq!(contract.buyback(2505), Err(Error::ReserveRatioTooLow));
+        }
+
+        #[ink::test]
+        pub fn treasury_spends_need_approval (){
+            let mut contract = deploy(1000);
+            let accounts = default_accounts();
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(contract_id(), 8000);
+            assert_eq!(contract.transfer(accounts.bob, 400), Ok(()));
+            assert_eq!(contract.transfer(accounts.charlie, 200), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.treasury_spend(accounts.eve, 1000), Ok(0));
+            assert_eq!(contract.execute_treasury_spend(1), Err(Error::ProposalNotFound));
+
+            //400 of the needed 500
+            assert_eq!(contract.approve_treasury_spend(0), Ok(()));
+            assert_eq!(contract.approve_treasury_spend(0), Err(Error::AccountAlreadyVoted));
+            assert_eq!(contract.execute_treasury_spend(0), Err(Error::QuorumNotReached));
+            //approving locks the balance so it can't approve twice from another account
+            assert_eq!(contract.transfer(accounts.django, 1), Err(Error::BalanceLocked));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.approve_treasury_spend(0), Ok(()));
+            assert_eq!(contract.spend_proposal(0).map(|spend| spend.approvals), Some(600));
+            assert_eq!(contract.execute_treasury_spend(0), Ok(()));
+            assert_eq!(contract.execute_treasury_spend(0), Err(Error::ProposalAlreadyExecuted));
+            assert_eq!(contract.treasury_balance(), 7000);
+
+            //unapproved proposals expire
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.treasury_spend(accounts.eve, 1000), Ok(1));
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(SPEND_APPROVAL_PERIOD);
+            assert_eq!(contract.approve_treasury_spend(1), Err(Error::ProposalExpired));
+            assert_eq!(contract.transfer(accounts.django, 1), Ok(()));
+        }
+
+        #[ink::test]
+        pub fn buys_stop_at_the_cap (){
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(contract_id());
+            assert!(matches!(Erc20::new_with_pricing(1001, 0, 5, PricingCurve::Flat, 1000), Err(Error::CapExceeded)));
+            let mut contract = Erc20::new_with_pricing(800, 0, 5, PricingCurve::Flat, 1000).unwrap();
+            let accounts = default_accounts();
+            assert_eq!(contract.remaining_mintable(), 200);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+
+            //the whole amount counts toward the cap, the 1% fee included
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(1005);
+            assert_eq!(contract.buy(201), Err(Error::CapExceeded));
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(500);
+            assert_eq!(contract.buy(100), Ok(()));
+            assert_eq!(contract.balance_of(accounts.bob), 99);
+            assert_eq!(contract.balance_of(contract.fee_pool()), 1);
+            //value beyond the cap is refunded, the last token of the cap goes to the fee
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(contract_id(), 1000);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(600);
+            assert_eq!(contract.buy_with_value(0), Ok(99));
+            assert_eq!(ink::env::balance::<ink::env::DefaultEnvironment>(), 900);
+            assert_eq!(contract.balance_of(contract.fee_pool()), 2);
+            assert_eq!(contract.total_supply(), 1000);
+            assert_eq!(contract.remaining_mintable(), 0);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(5);
+            assert_eq!(contract.buy(1), Err(Error::CapExceeded));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.mint(1), Err(Error::CapExceeded));
+            assert_supply_invariant(&contract);
+        }
+
+        #[ink::test]
+        pub fn max_supply_changes_are_timelocked (){
+            let mut contract = deploy(1000);
+            assert_eq!(contract.apply_max_supply(), Err(Error::NoPendingChange));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(bob());
+            assert_eq!(contract.propose_max_supply(2000), Err(Error::NotAdmin));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(alice());
+            assert_eq!(contract.propose_max_supply(999), Ok(()));
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(MAX_SUPPLY_TIMELOCK);
+            assert_eq!(contract.apply_max_supply(), Err(Error::CapExceeded));
+
+            assert_eq!(contract.propose_max_supply(2000), Ok(()));
+            assert_eq!(contract.apply_max_supply(), Err(Error::TimelockNotElapsed));
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(2 * MAX_SUPPLY_TIMELOCK);
+            //anyone applies it
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(bob());
+            assert_eq!(contract.apply_max_supply(), Ok(()));
+            assert_eq!(contract.max_supply(), 2000);
+            assert_eq!(contract.remaining_mintable(), 1000);
+            assert_eq!(contract.pending_max_supply(), None);
+        }
+
+        #[ink::test]
+        pub fn presale_buys_need_a_valid_proof (){
+            let mut contract = deploy(1000);
+            let accounts = default_accounts();
+            let bob_leaf = presale_leaf(accounts.bob, 100, 2);
+            let charlie_leaf = presale_leaf(accounts.charlie, 50, 3);
+            let root = hash_pair(&bob_leaf, &charlie_leaf);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.start_presale(root, 10, 20, None), Err(Error::NotAdmin));
+            assert_eq!(contract.presale_buy(10, 100, 2, vec![charlie_leaf]), Err(Error::PresaleNotActive));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.start_presale(root, 20, 20, None), Err(Error::InvalidPresale));
+            assert_eq!(contract.start_presale(root, 10, 20, None), Ok(0));
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(10);
+            assert!(contract.presale_active());
+            //a running presale can't be replaced
+            assert_eq!(contract.start_presale(root, 10, 30, None), Err(Error::PresaleOngoing));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(20);
+            //public buys wait for the presale
+            assert_eq!(contract.buy(4), Err(Error::PresaleOngoing));
+            //the leaf commits to allocation and price
+            assert_eq!(contract.presale_buy(10, 100, 1, vec![charlie_leaf]), Err(Error::InvalidProof));
+            assert_eq!(contract.presale_buy(10, 1000, 2, vec![charlie_leaf]), Err(Error::InvalidProof));
+            assert_eq!(contract.presale_buy(10, 100, 2, vec![bob_leaf]), Err(Error::InvalidProof));
+            assert_eq!(contract.presale_buy(11, 100, 2, vec![charlie_leaf]), Err(Error::ExactAmountOfWeiRequired));
+            assert_eq!(contract.presale_buy(10, 100, 2, vec![charlie_leaf]), Ok(()));
+            assert_eq!(contract.balance_of(accounts.bob), 10);
+            assert_eq!(contract.presale_purchased(accounts.bob), 10);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(182);
+            assert_eq!(contract.presale_buy(91, 100, 2, vec![charlie_leaf]), Err(Error::AllocationExceeded));
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(180);
+            assert_eq!(contract.presale_buy(90, 100, 2, vec![charlie_leaf]), Ok(()));
+            assert_supply_invariant(&contract);
+
+            //charlie can't use bob's leaf
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(20);
+            assert_eq!(contract.presale_buy(10, 100, 2, vec![charlie_leaf]), Err(Error::InvalidProof));
+
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(20);
+            assert_eq!(contract.presale_buy(10, 50, 3, vec![bob_leaf]), Err(Error::PresaleNotActive));
+
+            //the next presale starts with fresh allocations
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.start_presale(root, 20, 30, None), Ok(1));
+            assert_eq!(contract.presale_purchased(accounts.bob), 0);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(200);
+            assert_eq!(contract.presale_buy(100, 100, 2, vec![charlie_leaf]), Ok(()));
+            assert_eq!(contract.balance_of(accounts.bob), 200);
+        }
+
+        #[ink::test]
+        pub fn presale_purchases_can_vest (){
+            let mut contract = deploy(1000);
+            let accounts = default_accounts();
+            let bob_leaf = presale_leaf(accounts.bob, 1000, 1);
+            let charlie_leaf = presale_leaf(accounts.charlie, 1000, 1);
+            let root = hash_pair(&bob_leaf, &charlie_leaf);
+            let terms = VestingTerms { cliff: 50, duration: 1000 };
+            assert_eq!(contract.start_presale(root, 0, 100, Some(terms)), Ok(0));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(600);
+            assert_eq!(contract.presale_buy(600, 1000, 1, vec![charlie_leaf]), Ok(()));
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(400);
+            assert_eq!(contract.presale_buy(400, 1000, 1, vec![charlie_leaf]), Ok(()));
+            assert_eq!(contract.vesting_schedule(accounts.bob), Some(VestingSchedule { total: 1000, start: 100, cliff: 50, duration: 1000 }));
+
+            //nothing before the cliff
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(149);
+            assert_eq!(contract.locked_balance(accounts.bob), 1000);
+            assert_eq!(contract.transfer(accounts.django, 1), Err(Error::BalanceLocked));
+            assert_eq!(contract.approve(accounts.django, 1), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(contract.transfer_from(accounts.bob, accounts.django, 1), Err(Error::BalanceLocked));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.sell(1, 0), Err(Error::BalanceLocked));
+            //linear after it
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(350);
+            assert_eq!(contract.locked_balance(accounts.bob), 750);
+            assert_eq!(contract.transfer(accounts.django, 251), Err(Error::BalanceLocked));
+            assert_eq!(contract.transfer(accounts.django, 250), Ok(()));
+
+            //a later presale can't pull new purchases into the running schedule
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.start_presale(root, 350, 400, Some(terms)), Ok(1));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(100);
+            assert_eq!(contract.presale_buy(100, 1000, 1, vec![charlie_leaf]), Err(Error::VestingScheduleMismatch));
+            assert_eq!(contract.balance_of(accounts.bob), 750);
+
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1100);
+            assert_eq!(contract.locked_balance(accounts.bob), 0);
+            assert_eq!(contract.transfer(accounts.django, 750), Ok(()));
+
+            //once vested, the next presale gets its own schedule
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.start_presale(root, 1100, 1200, Some(terms)), Ok(2));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.presale_buy(100, 1000, 1, vec![charlie_leaf]), Ok(()));
+            assert_eq!(contract.vesting_schedule(accounts.bob), Some(VestingSchedule { total: 100, start: 1200, cliff: 50, duration: 1000 }));
+        }
+
+        #[ink::test]
+        pub fn airdrop_claims_are_tracked_in_a_bitmap (){
+            let mut contract = deploy(1000);
+            let accounts = default_accounts();
+            let bob_leaf = airdrop_leaf(0, accounts.bob, 100);
+            let charlie_leaf = airdrop_leaf(300, accounts.charlie, 50);
+            let root = hash_pair(&bob_leaf, &charlie_leaf);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.create_airdrop(root, 150, 100), Err(Error::NotAdmin));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.create_airdrop(root, 1001, 100), Err(Error::InsufficientBalance));
+            assert_eq!(contract.create_airdrop(root, 200, 0), Err(Error::AirdropExpired));
+            //locked tokens can't fund a campaign
+            assert_eq!(contract.set_lock(*b"vesting ", accounts.alice, 900, 10), Ok(()));
+            assert_eq!(contract.create_airdrop(root, 200, 100), Err(Error::BalanceLocked));
+            assert_eq!(contract.remove_lock(*b"vesting ", accounts.alice), Ok(()));
+            assert_eq!(contract.create_airdrop(root, 200, 100), Ok(0));
+            assert_eq!(contract.balance_of(contract_id()), 200);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.claim_airdrop(1, 0, 100, vec![charlie_leaf]), Err(Error::AirdropNotFound));
+            assert_eq!(contract.claim_airdrop(0, 0, 101, vec![charlie_leaf]), Err(Error::InvalidProof));
+            assert_eq!(contract.claim_airdrop(0, 1, 100, vec![charlie_leaf]), Err(Error::InvalidProof));
+            assert_eq!(contract.claim_airdrop(0, 0, 100, vec![charlie_leaf]), Ok(()));
+            assert_eq!(contract.claim_airdrop(0, 0, 100, vec![charlie_leaf]), Err(Error::AlreadyClaimed));
+            assert_eq!(contract.balance_of(accounts.bob), 100);
+            assert!(contract.is_airdrop_claimed(0, 0));
+            assert!(!contract.is_airdrop_claimed(0, 256));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.claim_airdrop(0, 300, 50, vec![bob_leaf]), Ok(()));
+            assert!(contract.is_airdrop_claimed(0, 300));
+            assert!(!contract.is_airdrop_claimed(0, 44));
+            assert_eq!(contract.airdrop(0).map(|airdrop| airdrop.claimed), Some(150));
+            assert_supply_invariant(&contract);
+        }
+
+        #[ink::test]
+        pub fn expired_airdrops_are_swept_to_the_treasury (){
+            let mut contract = deploy(1000);
+            let accounts = default_accounts();
+            let bob_leaf = airdrop_leaf(0, accounts.bob, 100);
+            let charlie_leaf = airdrop_leaf(1, accounts.charlie, 50);
+            let root = hash_pair(&bob_leaf, &charlie_leaf);
+            assert_eq!(contract.set_treasury(accounts.eve), Ok(()));
+            assert_eq!(contract.create_airdrop(root, 150, 100), Ok(0));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.claim_airdrop(0, 0, 100, vec![charlie_leaf]), Ok(()));
+            assert_eq!(contract.sweep_airdrop(0), Err(Error::AirdropNotExpired));
+
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(100);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.claim_airdrop(0, 1, 50, vec![bob_leaf]), Err(Error::AirdropExpired));
+            assert_eq!(contract.sweep_airdrop(0), Ok(50));
+            assert_eq!(contract.balance_of(accounts.eve), 50);
+            assert_eq!(contract.balance_of(contract_id()), 0);
+            assert_eq!(contract.sweep_airdrop(0), Ok(0));
+            assert_supply_invariant(&contract);
+        }
+
+        #[ink::test]
+        pub fn dutch_auction_refunds_down_to_the_clearing_price (){
+            let mut contract = deploy(100);
+            let accounts = default_accounts();
+            assert_eq!(contract.start_auction(200, 100, 20, 10, 30, true), Err(Error::InvalidAuction));
+            assert_eq!(contract.start_auction(100, 200, 10, 20, 30, true), Err(Error::InvalidAuction));
+            assert_eq!(contract.start_auction(100, 200, 20, 10, 30, true), Ok(0));
+            assert_eq!(contract.auction_price(), None);
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.bob, 1000);
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.charlie, 1000);
+
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(100);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(200);
+            assert_eq!(contract.buy(10), Ok(()));
+
+            //halfway down the price is 15
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(150);
+            assert_eq!(contract.auction_price(), Some(15));
+            assert_eq!(contract.claim_auction_refund(), Err(Error::AuctionNotEnded));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.quote_buy(21), Err(Error::AuctionSupplyExceeded));
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(310);
+            assert_eq!(contract.buy_with_value(0), Ok(20));
+            assert_eq!(ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.charlie), Ok(700));
+            assert_eq!(contract.auction().and_then(|auction| auction.sold_out_price), Some(15));
+            //everything paid stays set aside until the buyers settle
+            assert_eq!(contract.auction_refunds_owed(), 500);
+            assert_eq!(contract.required_reserve(), Ok(650));
+            assert_eq!(contract.treasury_surplus(), Ok(1000500 - 650 - 500));
+            assert_eq!(contract.claim_auction_refund(), Ok(0));
+            assert_eq!(contract.auction_refunds_owed(), 200);
+
+            //sold out, so the curve is back
+            assert_eq!(contract.auction_price(), None);
+            assert_eq!(contract.quote_buy(1), Ok(5));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.claim_auction_refund(), Ok(50));
+            assert_eq!(ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob), Ok(850));
+            assert_eq!(contract.auction_refunds_owed(), 0);
+            assert_eq!(contract.claim_auction_refund(), Ok(0));
+            assert_supply_invariant(&contract);
+        }
+
+        #[ink::test]
+        pub fn unsold_auctions_clear_at_the_floor (){
+            let mut contract = deploy(100);
+            let accounts = default_accounts();
+            assert_eq!(contract.start_auction(100, 200, 20, 10, 30, true), Ok(0));
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.bob, 1000);
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(100);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(200);
+            assert_eq!(contract.buy(10), Ok(()));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.start_auction(300, 400, 20, 10, 30, true), Err(Error::AuctionOngoing));
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(200);
+            assert_eq!(contract.start_auction(300, 400, 20, 10, 30, true), Ok(1));
+
+            //joining the next auction settles the old refund at the old floor
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(300);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(20);
+            assert_eq!(contract.buy(1), Ok(()));
+            assert_eq!(ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob), Ok(880));
+            assert_eq!(contract.auction_contribution(accounts.bob), Some(AuctionContribution { auction: 1, paid: 20, bought: 1 }));
+        }
+
+        #[ink::test]
+        pub fn trades_are_rate_limited_per_account (){
+            let mut contract = deploy(100);
+            let accounts = default_accounts();
+            let limits = RateLimits { window: 100, max_bought_per_window: 10, max_sold_per_window: 5, max_volume_per_block: 0 };
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.set_rate_limits(limits), Err(Error::NotAdmin));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.set_rate_limits(limits), Ok(()));
+
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.bob, 1000);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(50);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(40);
+            assert_eq!(contract.buy(8), Ok(()));
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(15);
+            assert_eq!(contract.buy(3), Err(Error::RateLimited(150)));
+            assert_eq!(contract.sell(5, 0), Ok(()));
+            assert_eq!(contract.sell(1, 0), Err(Error::RateLimited(150)));
+            assert_eq!(contract.trade_window(accounts.bob), TradeWindow { start: 50, bought: 8, sold: 5 });
+
+            //the window resets on the next trade after it expired
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(150);
+            assert_eq!(contract.trade_window(accounts.bob), TradeWindow { start: 150, bought: 0, sold: 0 });
+            assert_eq!(contract.sell(1, 0), Ok(()));
+            //other accounts have their own window
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.sell(5, 0), Ok(()));
+        }
+
+        #[ink::test]
+        pub fn trades_are_rate_limited_per_block (){
+            let mut contract = deploy(100);
+            let accounts = default_accounts();
+            assert_eq!(contract.set_rate_limits(RateLimits { max_volume_per_block: 10, ..Default::default() }), Ok(()));
+            assert_eq!(contract.transfer(accounts.bob, 10), Ok(()));
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(7);
+            assert_eq!(contract.sell(6, 0), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.sell(5, 0), Err(Error::BlockVolumeExceeded));
+            assert_eq!(contract.sell(4, 0), Ok(()));
+
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(contract.sell(5, 0), Ok(()));
+        }
+
+        #[ink::test]
+        pub fn staking_emits_rewards_by_share (){
+            let mut contract = deploy(100);
+            let accounts = default_accounts();
+            assert_eq!(contract.transfer(accounts.bob, 30), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.set_staking_params(10, SPEND_APPROVAL_PERIOD), Err(Error::NotAdmin));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.set_staking_params(10, SPEND_APPROVAL_PERIOD), Ok(()));
+
+            assert_eq!(contract.stake(10), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.stake(31), Err(Error::InsufficientBalance));
+            assert_eq!(contract.stake(30), Ok(()));
             assert_eq!(contract.balance_of(accounts.bob), 0);
-            assert_eq!(contract.approve(accounts.bob, 20), Ok(()));
-            // transfer method
-            assert_eq!(contract.transfer_from(accounts.alice, accounts.bob, 20), Ok(()));
-            //eventual balance
+            assert_eq!(contract.total_staked(), 40);
+
+            //40 per 4 seconds, a quarter to alice
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(4);
+            assert_eq!(contract.pending_staking_rewards(accounts.bob), 30);
+            assert_eq!(contract.claim_staking_rewards(), Ok(30));
+            assert_eq!(contract.balance_of(accounts.bob), 30);
+            assert_eq!(contract.total_supply(), 140);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.pending_staking_rewards(accounts.alice), 10);
+            assert_supply_invariant(&contract);
+
+            //rewards stop at the cap
+            assert_eq!(contract.propose_max_supply(150), Ok(()));
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(4 + MAX_SUPPLY_TIMELOCK);
+            assert_eq!(contract.apply_max_supply(), Ok(()));
+            //10 more shared 1:3
+            assert_eq!(contract.claim_staking_rewards(), Ok(12));
+            assert_eq!(contract.total_supply(), 150);
+        }
+
+        #[ink::test]
+        pub fn unstaked_tokens_unbond_before_withdrawal (){
+            let mut contract = deploy(100);
+            let accounts = default_accounts();
+            //unbonding has to outlast a round and a spend proposal
+            assert_eq!(contract.set_staking_params(0, 50), Err(Error::InvalidUnbondingPeriod));
+            assert_eq!(contract.set_staking_params(0, SPEND_APPROVAL_PERIOD), Ok(()));
+            assert_eq!(contract.transfer(accounts.bob, 10), Ok(()));
+            assert_eq!(contract.transfer(accounts.charlie, 5), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.stake(10), Ok(()));
+            //staked tokens still vote
+            assert_eq!(contract.voting_power(accounts.bob), 10);
+            assert_eq!(contract.initiate_voting(50), Ok(()));
+            assert_eq!(contract.votes_for(50), 10);
+
+            //and can't be moved on to vote a second time
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(10);
+            assert_eq!(contract.unstake(4), Err(Error::BalanceLocked));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.vote(50), Ok(()));
+            assert_eq!(contract.votes_for(50), 15);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            let unstake_at = contract.voting_end_time + 1;
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(unstake_at);
+            assert_eq!(contract.unstake(11), Err(Error::InsufficientStake));
+            assert_eq!(contract.unstake(4), Ok(()));
+            assert_eq!(contract.voting_power(accounts.bob), 6);
+            assert_eq!(contract.unbonding(accounts.bob), (4, unstake_at + SPEND_APPROVAL_PERIOD));
+            assert_eq!(contract.withdraw_unbonded(), Err(Error::UnbondingNotElapsed));
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(unstake_at + SPEND_APPROVAL_PERIOD);
+            assert_eq!(contract.withdraw_unbonded(), Ok(4));
+            assert_eq!(contract.balance_of(accounts.bob), 4);
+            assert_eq!(contract.withdraw_unbonded(), Ok(0));
+            assert_supply_invariant(&contract);
+        }
+
+        #[ink::test]
+        pub fn named_locks_restrict_spending (){
+            let mut contract = deploy(100);
+            let accounts = default_accounts();
+            assert_eq!(contract.transfer(accounts.bob, 50), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.set_lock(*b"staking ", accounts.bob, 10, 100), Err(Error::NotLockManager));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.set_lock_manager(accounts.charlie, true), Ok(()));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.set_lock(VOTE_LOCK, accounts.bob, 0, 0), Err(Error::ReservedLockId));
+            assert_eq!(contract.remove_lock(SPEND_LOCK, accounts.bob), Err(Error::ReservedLockId));
+            assert_eq!(contract.set_lock(*b"staking ", accounts.bob, 20, 100), Ok(()));
+            assert_eq!(contract.set_lock(*b"vesting ", accounts.bob, 30, 50), Ok(()));
+            //the largest lock wins, they don't add up
+            assert_eq!(contract.free_balance(accounts.bob), 20);
+            assert_eq!(contract.locks_of(accounts.bob).len(), 2);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.transfer(accounts.django, 21), Err(Error::BalanceLocked));
+            assert_eq!(contract.sell(21, 0), Err(Error::BalanceLocked));
+            assert_eq!(contract.stake(21), Err(Error::BalanceLocked));
+            assert_eq!(contract.transfer(accounts.django, 10), Ok(()));
+
+            //expired locks stop counting
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(50);
+            assert_eq!(contract.locks_of(accounts.bob), vec![BalanceLock { id: *b"staking ", amount: 20, until: 100 }]);
+            assert_eq!(contract.free_balance(accounts.bob), 20);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.remove_lock(*b"staking ", accounts.bob), Ok(()));
+            assert_eq!(contract.free_balance(accounts.bob), 40);
+            assert_eq!(contract.locks_of(accounts.bob), vec![]);
+        }
+
+        #[ink::test]
+        pub fn voting_locks_the_voted_balance (){
+            let mut contract = deploy(100);
+            let accounts = default_accounts();
+            assert_eq!(contract.transfer(accounts.bob, 10), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.initiate_voting(50), Ok(()));
+            assert_eq!(contract.locks_of(accounts.bob), vec![BalanceLock { id: VOTE_LOCK, amount: 10, until: contract.voting_end_time + 1 }]);
+            assert_eq!(contract.transfer(accounts.charlie, 1), Err(Error::BalanceLocked));
+
+            //tokens received after voting stay free
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.transfer(accounts.bob, 5), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.transfer(accounts.charlie, 5), Ok(()));
+
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(contract.voting_end_time + 1);
+            assert_eq!(contract.transfer(accounts.charlie, 10), Ok(()));
+        }
+
+        #[ink::test]
+        pub fn reserves_can_be_returned_or_slashed (){
+            let mut contract = deploy(100);
+            let accounts = default_accounts();
+            assert_eq!(contract.set_treasury(accounts.eve), Ok(()));
+            assert_eq!(contract.transfer(accounts.bob, 50), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.reserve(accounts.bob, 10), Err(Error::NotLockManager));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.reserve(accounts.bob, 51), Err(Error::InsufficientBalance));
+            assert_eq!(contract.reserve(accounts.bob, 30), Ok(()));
             assert_eq!(contract.balance_of(accounts.bob), 20);
+            assert_eq!(contract.reserved_balance(accounts.bob), 30);
+            assert_eq!(contract.voting_power(accounts.bob), 20);
+            assert_supply_invariant(&contract);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.transfer(accounts.charlie, 21), Err(Error::InsufficientBalance));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.unreserve(accounts.bob, 10), Ok(10));
+            assert_eq!(contract.slash_reserved(accounts.bob, 5, false), Ok(5));
+            assert_eq!(contract.balance_of(accounts.eve), 5);
+            //only what is reserved can be slashed
+            assert_eq!(contract.slash_reserved(accounts.bob, 100, true), Ok(15));
+            assert_eq!(contract.fee_to_burn(), 15);
+            assert_eq!(contract.balance_of(contract_id()), 15);
+            assert_eq!(contract.balance_of(accounts.bob), 30);
+            assert_eq!(contract.total_reserved(), 0);
+            assert_supply_invariant(&contract);
         }
 
         #[ink::test]
-        fn transfer_from_error_when_insfficient_funds(){
-            let mut contract = Erc20::new(0);
-            let accounts = 
-                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        pub fn proposal_deposits_are_refunded_on_quorum (){
+            let mut contract = deploy(100);
+            let accounts = default_accounts();
+            let params = ProposalParams { deposit: 5, quorum: Permill::from_percent(30), cooldown: 1000 };
+            assert_eq!(contract.set_proposal_params(params), Ok(()));
+            assert_eq!(contract.transfer(accounts.bob, 14), Ok(()));
+            assert_eq!(contract.transfer(accounts.charlie, 21), Ok(()));
 
-            assert_eq!(contract.balance_of(accounts.bob), 0);
-            assert!(contract.transfer_from(accounts.alice, accounts.bob, 100).is_err());
-            assert_eq!(contract.transfer_from(accounts.alice, accounts.bob, 100), Err(Error::InsufficientBalance));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.initiate_voting(50), Ok(()));
+            //the deposit is out of bob's vote
+            assert_eq!(contract.reserved_balance(accounts.bob), 5);
+            assert_eq!(contract.votes_for(50), 9);
+            //9 + 21 is exactly the quorum
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.vote(60), Ok(()));
+
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(contract.voting_end_time);
+            assert_eq!(contract.end_voting(), Ok(()));
+            assert_eq!(contract.reserved_balance(accounts.bob), 0);
+            assert_eq!(contract.balance_of(accounts.bob), 14);
+            assert_supply_invariant(&contract);
         }
 
         #[ink::test]
-        fn transfer_from_error_insufficient_allowance(){
-            let mut contract = Erc20::new(100);
-            let accounts = 
-                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
-            assert_eq!(contract.approve(accounts.bob, 10), Ok(()));
-            assert_eq!(contract.allowances(accounts.alice, accounts.bob), 10);
-            assert_eq!(contract.transfer_from(accounts.alice, accounts.bob, 100), Err(Error::InsufficientAllowance));
+        pub fn proposal_deposits_are_slashed_without_quorum (){
+            let mut contract = deploy(100);
+            let accounts = default_accounts();
+            let params = ProposalParams { deposit: 5, quorum: Permill::from_percent(30), cooldown: 100000 };
+            assert_eq!(contract.set_proposal_params(params), Ok(()));
+            assert_eq!(contract.transfer(accounts.bob, 20), Ok(()));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.initiate_voting(50), Ok(()));
+            let end = contract.voting_end_time;
+            let price = contract.token_price;
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(end);
+            assert_eq!(contract.end_voting(), Ok(()));
+            //missing quorum keeps the old price
+            assert_eq!(contract.token_price, price);
+            assert_eq!(contract.reserved_balance(accounts.bob), 0);
+            assert_eq!(contract.balance_of(accounts.bob), 15);
+            assert_eq!(contract.fee_to_burn(), 5);
+            assert_supply_invariant(&contract);
+
+            //bob waits out the cooldown, others don't have to
+            assert_eq!(contract.initiate_voting(50), Err(Error::ProposalCooldown(100000)));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.initiate_voting(50), Ok(()));
         }
 
+        #[ink::test]
+        pub fn initiators_must_still_vote_after_the_deposit (){
+            let mut contract = deploy(100);
+            let accounts = default_accounts();
+            let params = ProposalParams { deposit: 6, quorum: Permill::from_parts(0), cooldown: 0 };
+            assert_eq!(contract.set_proposal_params(params), Ok(()));
+            assert_eq!(contract.transfer(accounts.bob, 10), Ok(()));
+
+            //4 left is under the 5% voting threshold
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.initiate_voting(0), Err(Error::InsufficientBalance));
+            assert_eq!(contract.reserved_balance(accounts.bob), 0);
+            assert!(!contract.is_voting_happening);
+        }
 
         #[ink::test]
-        fn approve_happens(){
-            let mut contract = Erc20::new(100);
-            let accounts = 
-                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
-            assert_eq!(contract.approve(accounts.bob, 10), Ok(()));
-            assert_eq!(contract.allowances(accounts.alice, accounts.bob), 10);
+        pub fn votes_add_up_within_a_round (){
+            let mut contract = deploy(100);
+            let accounts = default_accounts();
+            assert_eq!(contract.transfer(accounts.bob, 20), Ok(()));
+            assert_eq!(contract.transfer(accounts.charlie, 10), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.initiate_voting(50), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.vote(50), Ok(()));
+            assert_eq!(contract.votes_for(50), 30);
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(contract.voting_end_time);
+            assert_eq!(contract.end_voting(), Ok(()));
+            assert_eq!(contract.token_price, 50);
+
+            //the next round starts from scratch
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(contract.voting_end_time + 10);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.initiate_voting(70), Ok(()));
+            assert_eq!(contract.votes_for(50), 0);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.vote(80), Ok(()));
+            assert_eq!(contract.votes_for(80), 20);
+            assert_eq!(contract.current_winner, 70);
         }
 
         #[ink::test]
-        fn approve_error(){
-            let mut contract = Erc20::new(100);
-            let accounts = 
-                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
-            assert!(contract.approve(accounts.bob, 110).is_err());
-            assert_eq!(contract.approve(accounts.bob, 110), Err(Error::InsufficientBalance));
+        pub fn blocked_accounts_cannot_send_or_receive (){
+            let mut contract = deploy(100);
+            let accounts = default_accounts();
+            assert_eq!(contract.transfer(accounts.bob, 20), Ok(()));
+            assert_eq!(contract.set_compliance(accounts.frank), Ok(()));
+            assert_eq!(contract.set_blocked(accounts.bob, true), Err(Error::NotCompliance));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.frank);
+            assert_eq!(contract.set_blocked(accounts.bob, true), Ok(()));
+            assert!(contract.is_blocked(accounts.bob));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.transfer(accounts.bob, 1), Err(Error::RecipientNotAllowed));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.transfer(accounts.charlie, 1), Err(Error::SenderBlocked));
+            assert_eq!(contract.approve(accounts.charlie, 5), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.transfer_from(accounts.bob, accounts.charlie, 5), Err(Error::SenderBlocked));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.sell(1, 0), Err(Error::SenderBlocked));
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(5);
+            assert_eq!(contract.buy(1), Err(Error::RecipientNotAllowed));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.frank);
+            assert_eq!(contract.set_blocked(accounts.bob, false), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.transfer(accounts.charlie, 1), Ok(()));
         }
 
         #[ink::test]
-        fn initiate_voting_works(){
-            let mut contract = Erc20::new(100);
-            let accounts = 
-                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
-            let new_price: u128 = 50;
-            //is_voting false
-            assert_eq!(contract.is_voting_happening, false);
-            //transfer 1 token to bob
-            assert_eq!(contract.transfer(accounts.bob, 1), Ok(()));
-            assert_eq!(contract.balance_of(accounts.bob), 1);
-            //call the function, should return error
+        pub fn blocked_stakers_cannot_stake_or_collect (){
+            let mut contract = deploy(100);
+            let accounts = default_accounts();
+            assert_eq!(contract.transfer(accounts.bob, 30), Ok(()));
+            assert_eq!(contract.set_staking_params(10, SPEND_APPROVAL_PERIOD), Ok(()));
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
-            assert_eq!(contract.initiate_voting(new_price), Err(Error::InsufficientBalance));
-            //transfer 9 more tokens to bob
+            assert_eq!(contract.stake(20), Ok(()));
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(4);
+            assert_eq!(contract.unstake(20), Ok(()));
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(4 + SPEND_APPROVAL_PERIOD);
+
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
-            assert_eq!(contract.transfer(accounts.bob, 9), Ok(()));
-            assert_eq!(contract.balance_of(accounts.bob), 10);
-            //voting end time has not began to run yet
+            assert_eq!(contract.set_blocked(accounts.bob, true), Ok(()));
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
-            assert_eq!(contract.voting_end_time, 0);
-            //set block timestamp to 1
-            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1);
-            // successful initiate voting
-            assert_eq!(contract.initiate_voting(new_price), Ok(()));
+            assert_eq!(contract.stake(10), Err(Error::SenderBlocked));
+            assert_eq!(contract.claim_staking_rewards(), Err(Error::RecipientNotAllowed));
+            assert_eq!(contract.withdraw_unbonded(), Err(Error::RecipientNotAllowed));
+            assert_eq!(contract.balance_of(accounts.bob), 10);
 
-            // ink::env::debug_println!("block time stamp: {}", ink::env::block_timestamp::<ink::env::DefaultEnvironment>()); 
-            
-            //is_voting true
-            assert_eq!(contract.is_voting_happening, true);
-            //time to vote began
-            assert_eq!(contract.voting_end_time, contract.get_current_timestemp() + contract.time_to_vote);
-            assert_eq!(contract.voting_begin_time, contract.get_current_timestemp());
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.set_blocked(accounts.bob, false), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.claim_staking_rewards(), Ok(40));
+            assert_eq!(contract.withdraw_unbonded(), Ok(20));
         }
 
         #[ink::test]
-        fn vote_accepted(){
-            let mut contract = Erc20::new(100);
+        pub fn blocked_accounts_cannot_collect_fees_or_reserves (){
+            let mut contract = deploy(10000);
             let accounts = default_accounts();
-            let mut new_price: u128 = 50;
+            let fees = FeeSchedule { transfer: Permill::from_percent(10), ..contract.fees() };
+            assert_eq!(contract.set_fees(FeeSchedule { split: FeeSplit { burn: Permill::from_percent(50), treasury: Permill::from_parts(0), holders: Permill::from_percent(50) }, ..fees }), Ok(()));
+            assert_eq!(contract.set_lock_manager(accounts.alice, true), Ok(()));
+            assert_eq!(contract.transfer(accounts.bob, 1000), Ok(()));
+            assert_eq!(contract.transfer(accounts.django, 2000), Ok(()));
+            assert_eq!(contract.reserve(accounts.bob, 10), Ok(()));
+            assert!(contract.claimable_rewards(accounts.bob) > 0);
+            assert!(contract.keeper_incentive.mul_floor(contract.fee_to_burn()) > 0);
+            assert_eq!(contract.set_blocked(accounts.bob, true), Ok(()));
 
-            //call the vote function when voting is not happening
-            assert_eq!(contract.transfer(accounts.charlie, 10), Ok(()));
+            assert_eq!(contract.unreserve(accounts.bob, 10), Err(Error::RecipientNotAllowed));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.claim_rewards(), Err(Error::RecipientNotAllowed));
+            //the keeper incentive is a payout too
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(604800);
+            assert_eq!(contract.finalize_burn_epoch(), Err(Error::RecipientNotAllowed));
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
-            assert_eq!(contract.vote(new_price), Err(Error::VotingIsNotOngoing));
-            assert_eq!(contract.initiate_voting(new_price), Ok(()));
-            assert_eq!(contract.current_winner, new_price);
-            assert_eq!(contract.votes.get(50).unwrap_or(0), 10);
-            
-            //set bob as contract caller
-            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
-            //call iniciate voting from bob's account
-            assert_eq!(contract.initiate_voting(new_price), Err(Error::VotingIsAlreadyOngoing));
-            assert_eq!(contract.balance_of(accounts.bob), 0);
-            //call the vote function
+            assert_eq!(contract.finalize_burn_epoch(), Ok(0));
+            assert_supply_invariant(&contract);
+        }
+
+        #[ink::test]
+        pub fn allowlist_mode_limits_who_can_hold (){
+            let mut contract = deploy(100);
+            let accounts = default_accounts();
+            assert_eq!(contract.transfer(accounts.bob, 20), Ok(()));
+            assert_eq!(contract.set_allowlist_only(true), Ok(()));
+            assert_eq!(contract.transfer(accounts.charlie, 1), Err(Error::RecipientNotAllowed));
+            assert_eq!(contract.mint(1), Err(Error::RecipientNotAllowed));
+            assert_eq!(contract.set_allowed(accounts.charlie, true), Ok(()));
+            assert_eq!(contract.transfer(accounts.charlie, 1), Ok(()));
+
+            //holders from before can still get out
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
-            assert_eq!(contract.vote(new_price), Err(Error::InsufficientBalance));
-            
-            //transfer 15 tokens to bob
+            assert_eq!(contract.transfer(accounts.charlie, 5), Ok(()));
+            assert_eq!(contract.sell(5, 0), Ok(()));
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(5);
+            assert_eq!(contract.buy(1), Err(Error::RecipientNotAllowed));
+            assert_eq!(contract.buy_with_value(0), Err(Error::RecipientNotAllowed));
+
+            let list_changes = ink::env::test::recorded_events().count();
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
-            assert_eq!(contract.transfer(accounts.bob, 15), Ok(()));
-            assert_eq!(contract.balance_of(accounts.bob), 15);
+            assert_eq!(contract.set_allowed(accounts.charlie, false), Ok(()));
+            assert_eq!(contract.set_allowlist_only(false), Ok(()));
+            assert_eq!(ink::env::test::recorded_events().count(), list_changes + 2);
+        }
+
+        #[ink::test]
+        pub fn mint_and_burn_are_restricted (){
+            let mut contract = deploy(100);
+            let accounts = default_accounts();
+
+            assert_eq!(contract.mint(50), Ok(()));
+            assert_eq!(contract.balance_of(accounts.alice), 150);
+            assert_supply_invariant(&contract);
+            assert_eq!(contract.transfer(accounts.bob, 10), Ok(()));
 
-            //change caller to bob
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
-            //he has not voted yet
-            assert_eq!(contract.already_voted.get(accounts.bob).unwrap_or_default(), false);
-            //call the vote function
-            new_price = 60;
-            assert_eq!(contract.vote(new_price), Ok(()));
-            //check the mapping: price option voted for == account voting power
-            assert_eq!(contract.votes.get(60), Some(contract.balance_of(bob())));
-            assert_eq!(contract.current_winner, 60);
-            assert_eq!(contract.votes.get(60).unwrap_or(0), 15);
-            //bob has already voted
-            assert_eq!(contract.already_voted.get(accounts.bob).unwrap_or_default(), true);
-            assert_eq!(contract.vote(new_price), Err(Error::AccountAlreadyVoted));
+            assert_eq!(contract.mint(50), Err(Error::NotAdmin));
+            assert_eq!(contract.burn(10, accounts.alice), Err(Error::NotAdmin));
+            assert_eq!(contract.burn(4, accounts.bob), Ok(()));
+            assert_eq!(contract.total_supply(), 146);
+            assert_supply_invariant(&contract);
+        }
 
-            //transfer 5 to charlie
-            assert_eq!(contract.balance_of(accounts.charlie), 10);
-            //with transfer
-            assert_eq!(contract.transfer(accounts.charlie, 5), Err(Error::AccountFrozenBecauseVoted));
-            assert_eq!(contract.approve(accounts.charlie, 5), Ok(()));
-            assert_eq!(contract.allowances(accounts.bob, accounts.charlie), 5);
-            //with transfer_from
-            assert_eq!(contract.transfer_from(accounts.bob, accounts.charlie, 5), Err(Error::AccountFrozenBecauseVoted));
-            assert_eq!(contract.balance_of(accounts.bob), 15);
-            assert_eq!(contract.balance_of(accounts.charlie), 10);
-            assert_eq!(contract.current_winner, 60);
+        #[test]
+        fn curve_costs_are_closed_form() {
+            let linear = PricingCurve::Linear { base_price: 10, slope: 1 };
+            //sum of 10 + s for s in 100..110
+            assert_eq!(linear.cost(0, 100, 110), Ok(1145));
+            assert_eq!(linear.cost(0, 100, 111), Ok(1145 + 120));
+            assert_eq!(linear.cost(0, 5, 5), Ok(0));
+            assert_eq!(linear.cost(0, 0, 0), Ok(0));
+            assert_eq!(linear.spot_price(0, 100), Ok(110));
+
+            let exponential = PricingCurve::Exponential { base_price: 100, growth: Permill::from_percent(10), step: 10 };
+            //10 units at 100 then 10 at 110
+            assert_eq!(exponential.cost(0, 0, 20), Ok(2100));
+            assert_eq!(exponential.cost(0, 15, 25), Ok(5 * 110 + 5 * 121));
+            assert_eq!(exponential.spot_price(0, 20), Ok(121));
 
+            assert_eq!(PricingCurve::Flat.cost(5, 1000, 1010), Ok(50));
+            assert_eq!(linear.cost(0, 0, u128::MAX), Err(Error::Overflow));
         }
 
         #[ink::test]
-        pub fn end_voting_success(){
-            let mut contract = Erc20::new(100);
-            let accounts: ink::env::test::DefaultAccounts<ink::env::DefaultEnvironment> = default_accounts();
-            let new_price: u128 = 50;
+        pub fn buy_and_sell_follow_the_curve () {
+            let mut contract = deploy_with_curve(100, PricingCurve::Linear { base_price: 10, slope: 1 });
+            let accounts = default_accounts();
+            assert_eq!(contract.quote_buy(10), Ok(1145));
 
-            //call the vote function when voting is not happening
-            assert_eq!(contract.transfer(accounts.bob, 10), Ok(()));
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.bob, 1145);
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
-            assert_eq!(contract.vote(new_price), Err(Error::VotingIsNotOngoing));
-            assert_eq!(contract.end_voting(), Err(Error::VotingIsNotOngoing));
-            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1);
-            assert_eq!(contract.initiate_voting(new_price), Ok(()));
-            
-            ink::env::debug_println!("voting_end_time: {}", contract.voting_end_time);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1145);
+            assert_eq!(contract.buy(10), Ok(()));
+            assert_eq!(contract.total_supply(), 110);
+            assert_eq!(contract.spot_price(), Ok(120));
+            //selling back walks down the same range
+            assert_eq!(contract.quote_sell(10), Ok(1145));
+            assert_eq!(contract.quote_sell(111), Err(Error::Underflow));
+            assert_eq!(contract.sell(5, 0), Ok(()));
+            assert_eq!(contract.total_supply(), 105);
+            assert_eq!(contract.quote_buy(5), Ok(115 + 116 + 117 + 118 + 119));
+        }
 
-            //checks the time has elapsed
-            assert_eq!(contract.end_voting(), Err(Error::TimeToVoteNotElapsed));
-            //logic for time travel
-            ink::env::debug_println!("block number: {}", ink::env::block_number::<ink::env::DefaultEnvironment>());
-            ink::env::debug_println!("block time stamp: {}", ink::env::block_timestamp::<ink::env::DefaultEnvironment>()); 
-            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+        #[ink::test]
+        pub fn voting_moves_the_curve_base_price () {
+            let mut contract = deploy_with_curve(100, PricingCurve::Exponential { base_price: 10, growth: Permill::from_percent(1), step: 1000 });
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1);
+            assert_eq!(contract.initiate_voting(20), Ok(()));
             ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(contract.voting_end_time);
-            ink::env::debug_println!("block number: {}", ink::env::block_number::<ink::env::DefaultEnvironment>());
-            ink::env::debug_println!("block time stamp: {}", ink::env::block_timestamp::<ink::env::DefaultEnvironment>());
             assert_eq!(contract.end_voting(), Ok(()));
-            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
-            ink::env::debug_println!("block number: {}", ink::env::block_number::<ink::env::DefaultEnvironment>());
-            ink::env::debug_println!("block time stamp: {}", ink::env::block_timestamp::<ink::env::DefaultEnvironment>());
+            assert_eq!(contract.pricing_curve(), PricingCurve::Exponential { base_price: 20, growth: Permill::from_percent(1), step: 1000 });
+            assert_eq!(contract.quote_buy(10), Ok(200));
+        }
 
-            assert_eq!(contract.voting_end_time, 0);
-            assert_eq!(contract.voting_begin_time, 0);
-            ink::env::debug_println!("contract.already_voted.get(accounts.bob): {}", contract.already_voted.get(accounts.bob).unwrap());
+        #[ink::test]
+        pub fn buy_with_value_refunds_dust () {
+            let mut contract = deploy(100);
+            let accounts = default_accounts();
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.bob, 1000);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
 
-            // assert_eq!(contract.already_voted.get(accounts.bob).unwrap_or(false), false);
-            assert_eq!(contract.is_voting_happening, false);
-            assert_eq!(contract.current_winner, 50);
-            assert_eq!(contract.votes.get(50).unwrap_or(0), 10);
+            //52 at price 5 buys 10, 2 comes back
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(52);
+            assert_eq!(contract.buy_with_value(11), Err(Error::SlippageExceeded));
+            assert_eq!(contract.buy_with_value(10), Ok(10));
+            assert_eq!(contract.balance_of(accounts.bob), 10);
+            assert_eq!(ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob), Ok(950));
+            assert_eq!(ink::env::balance::<ink::env::DefaultEnvironment>(), 1000050);
+            assert_supply_invariant(&contract);
+
+            //1000 buys 200, 2 of them are the fee
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.bob, 10000);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000);
+            assert_eq!(contract.buy_with_value(199), Err(Error::SlippageExceeded));
+            assert_eq!(contract.buy_with_value(198), Ok(198));
+            assert_eq!(contract.balance_of(accounts.bob), 208);
         }
 
         #[ink::test]
-        pub fn buy_works (){
-            let mut contract = Erc20::new(100);
-            let accounts: ink::env::test::DefaultAccounts<ink::env::DefaultEnvironment> = default_accounts();
+        pub fn tokens_for_value_inverts_the_curve () {
+            let contract = deploy_with_curve(100, PricingCurve::Linear { base_price: 10, slope: 1 });
+            assert_eq!(contract.tokens_for_value(1144), Ok(9));
+            assert_eq!(contract.tokens_for_value(1145), Ok(10));
+            assert_eq!(contract.tokens_for_value(0), Ok(0));
 
-    
-            //total supply and contract balance are in their initial values
-            assert_eq!(ink::env::balance::<ink::env::DefaultEnvironment>(), 1000000);
-            assert_eq!(contract.total_supply, 100);
+            let contract = deploy_with_curve(0, PricingCurve::Linear { base_price: 0, slope: 2 });
+            //0 + 2 + 4 + 6
+            assert_eq!(contract.tokens_for_value(12), Ok(4));
+        }
 
-            // assert_eq!(contract.transfer(accounts.bob, 50), Ok(()));
+        #[ink::test]
+        pub fn sell_respects_min_native_out () {
+            let mut contract = deploy(100);
+            let accounts = default_accounts();
+            assert_eq!(contract.transfer(accounts.bob, 10), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.sell(10, 51), Err(Error::SlippageExceeded));
+            assert_eq!(contract.sell(10, 50), Ok(()));
+        }
+
+        #[ink::test]
+        pub fn prices_are_per_whole_token () {
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(contract_id());
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(contract_id(), 1000000);
+            assert!(matches!(Erc20::new_with_pricing(100, 39, 1, PricingCurve::Flat, Balance::MAX), Err(Error::InvalidDecimals)));
+            //1 native unit per whole token, below one per token unit
+            let mut contract = Erc20::new_with_pricing(0, 12, 1, PricingCurve::Flat, Balance::MAX).unwrap();
+            let accounts = default_accounts();
+            let token: Balance = 1_000_000_000_000;
+            assert_eq!(contract.decimals(), 12);
+            assert_eq!(contract.quote_buy(5 * token), Ok(5));
+            //partial native units round against the trader
+            assert_eq!(contract.quote_buy(1), Ok(1));
+            assert_eq!(contract.tokens_for_value(5), Ok(5 * token));
 
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.bob, 1000);
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
-            //transfer of 50 ETH to the contract
-            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(50);
-            //incorrect amount passed to the buy method
-            assert_eq!(contract.buy(8), Err(Error::ExactAmountOfWeiRequired));
-            //correct amount passed to the buy method
-            assert_eq!(contract.buy(10), Ok(()));
-            //total supply +10
-            assert_eq!(contract.total_supply, 110);
-            //bob + 10
-            assert_eq!(contract.balance_of(accounts.bob), 10);
-            //check that contract has reveiced 50 ETH
-            assert_eq!(ink::env::balance::<ink::env::DefaultEnvironment>(), 1000050);            
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(5);
+            assert_eq!(contract.buy(5 * token), Ok(()));
+            assert_eq!(contract.quote_sell(1), Ok(0));
+            assert_eq!(contract.quote_sell(2 * token), Ok(2));
+        }
+
+        #[ink::test]
+        pub fn curve_prices_are_per_whole_token () {
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(contract_id());
+            let contract = Erc20::new_with_pricing(0, 3, 0, PricingCurve::Linear { base_price: 1000, slope: 1 }, Balance::MAX).unwrap();
+            //1000 units: (1000 * 1000 + 999 * 1000 / 2) / 10^3 = 1499.5, rounded up
+            assert_eq!(contract.quote_buy(1000), Ok(1500));
+            assert_eq!(contract.spot_price(), Ok(1000));
         }
 
         #[ink::test]
@@ -607,28 +4381,140 @@ mod erc20 {
 
             //bob's try with 0 balance
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
-            assert_eq!(contract.sell(10), Err(Error::InsufficientBalance));
+            assert_eq!(contract.sell(10, 0), Err(Error::InsufficientBalance));
             //transfer tokens to bob
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
             assert_eq!(contract.transfer(accounts.bob, 11), Ok(()));
             //second bob's try
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
-            assert_eq!(contract.sell(10), Ok(()));
+            let bob_native = ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob).unwrap_or_default();
+            let reserve = ink::env::balance::<ink::env::DefaultEnvironment>();
+            assert_eq!(contract.sell(10, 0), Ok(()));
+            //10 tokens * price 5 paid out of the reserve
+            assert_eq!(ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob), Ok(bob_native + 50));
+            assert_eq!(ink::env::balance::<ink::env::DefaultEnvironment>(), reserve - 50);
             
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
             
             assert_eq!(contract.total_supply, 90);
             
-            assert_eq!(contract.balance_of(accounts.alice), 90);
-            // assert_eq!(contract.balance_of(accounts.bob), 0); 
+            assert_eq!(contract.balance_of(accounts.alice), 89);
+            assert_eq!(contract.balance_of(accounts.bob), 1);
+            assert_eq!(ink::env::test::recorded_events().count(), 3);
         }
 
         #[ink::test]
-        pub fn burn_fee_works () {
+        pub fn sell_fails_when_reserve_is_short () {
             let mut contract = Erc20::new(100);
+            let accounts = default_accounts();
+            let contract_id = ink::env::test::callee::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(contract_id, 49);
+            assert_eq!(contract.transfer(accounts.bob, 10), Ok(()));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.sell(10, 0), Err(Error::InsufficientReserve));
+            assert_eq!(contract.balance_of(accounts.bob), 10);
+            assert_eq!(contract.total_supply(), 100);
+        }
+
+        #[test]
+        fn safe_math_boundaries() {
+            assert_eq!(safe_add(u128::MAX - 1, 1), Ok(u128::MAX));
+            assert_eq!(safe_add(u128::MAX, 1), Err(Error::Overflow));
+            assert_eq!(safe_sub(1, 1), Ok(0));
+            assert_eq!(safe_sub(0, 1), Err(Error::Underflow));
+            assert_eq!(safe_mul(u128::MAX, 1), Ok(u128::MAX));
+            assert_eq!(safe_mul(u128::MAX / 2 + 1, 2), Err(Error::Overflow));
+            assert_eq!(safe_div(7, 2), Ok(3));
+            assert_eq!(safe_div(7, 0), Err(Error::DivisionByZero));
+            assert_eq!(mul_div_floor(u128::MAX, 3, 4), Ok(u128::MAX / 4 * 3 + 2));
+            assert_eq!(mul_div_floor(7, 1, 0), Err(Error::DivisionByZero));
+        }
+
+        #[ink::test]
+        fn mint_and_burn_overflow_boundaries() {
+            let mut contract = Erc20::new(u128::MAX - 1);
+            assert_eq!(contract.mint(1), Ok(()));
+            assert_eq!(contract.total_supply(), u128::MAX);
+            assert_eq!(contract.mint(1), Err(Error::Overflow));
+
+            let mut contract = Erc20::new(10);
+            assert_eq!(contract.burn(11, alice()), Err(Error::Underflow));
+            assert_eq!(contract.burn(10, alice()), Ok(()));
+            assert_eq!(contract.total_supply(), 0);
+            assert_eq!(contract.burn(1, alice()), Err(Error::Underflow));
+        }
+
+        #[ink::test]
+        fn buy_and_sell_overflow_boundaries() {
+            let mut contract = Erc20::new(u128::MAX);
+            //amount * token_price
+            assert_eq!(contract.buy(u128::MAX / 5 + 1), Err(Error::Overflow));
+            //amount + fee
+            assert_eq!(contract.sell(u128::MAX, 0), Err(Error::Overflow));
+            //supply is already at the maximum
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(bob());
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(5);
+            assert_eq!(contract.buy(1), Err(Error::Overflow));
+        }
+
+        #[ink::test]
+        pub fn burn_fee_works () {
+            let mut contract = deploy(100_000);
             let accounts: ink::env::test::DefaultAccounts<ink::env::DefaultEnvironment> = default_accounts();
+            let sell_for_fee = |contract: &mut Erc20| {
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+                //1% of 10000
+                assert_eq!(contract.sell(10000, 0), Ok(()));
+            };
+
+            sell_for_fee(&mut contract);
+            assert_eq!(contract.fee_to_burn(), 100);
+            //the epoch hasn't ended yet
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(604799);
+            assert_eq!(contract.finalize_burn_epoch(), Err(Error::TimeForFeeBurnHasNotLapsed));
 
-            assert_eq!(contract.burn_fee_weekly(), Ok(()));
+            //anyone can finalize and gets 1% of the burn
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(604800);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            let supply = contract.total_supply();
+            assert_eq!(contract.finalize_burn_epoch(), Ok(0));
+            assert_eq!(contract.burn_history(0), Some(99));
+            assert_eq!(contract.balance_of(accounts.charlie), 1);
+            assert_eq!(contract.total_supply(), supply - 99);
+            assert_eq!(contract.fee_to_burn(), 0);
+            //only once per epoch
+            assert_eq!(contract.finalize_burn_epoch(), Err(Error::TimeForFeeBurnHasNotLapsed));
+
+            //a late finalization closes every elapsed epoch at once
+            sell_for_fee(&mut contract);
+            sell_for_fee(&mut contract);
+            assert_eq!(contract.set_burn_schedule(100, Permill::from_percent(10)), Ok(()));
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(604800 + 350);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.finalize_burn_epoch(), Ok(3));
+            assert_eq!(contract.burn_history(3), Some(180));
+            assert_eq!(contract.burn_history(1), None);
+            assert_eq!(contract.balance_of(accounts.charlie), 21);
+            assert_eq!(contract.current_burn_epoch(), (4, 604800 + 300));
+            assert_supply_invariant(&contract);
+
+            //50s into epoch 4, a shorter length can't end it retroactively
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.set_burn_schedule(50, Permill::from_percent(10)), Err(Error::InvalidBurnEpochLength));
+            assert_eq!(contract.set_burn_schedule(60, Permill::from_percent(10)), Ok(()));
+            assert_eq!(contract.current_epoch(), 4);
+            //unfinalized epochs close at the length they ran with
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(604800 + 360);
+            assert_eq!(contract.set_burn_schedule(100, Permill::from_percent(10)), Err(Error::BurnEpochNotFinalized));
+            assert_eq!(contract.finalize_burn_epoch(), Ok(4));
+            assert_eq!(contract.set_burn_schedule(100, Permill::from_percent(10)), Ok(()));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.set_burn_schedule(0, Permill::from_percent(1)), Err(Error::NotAdmin));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.set_burn_schedule(0, Permill::from_percent(1)), Err(Error::InvalidBurnEpochLength));
+            assert_eq!(contract.set_burn_schedule(100, Permill::from_percent(11)), Err(Error::FeeTooHigh));
         }
     }
 }